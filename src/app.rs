@@ -0,0 +1,91 @@
+//!
+//! Copyright (C) 2023-2025 cyberknight777
+//!
+//! SPDX-License-Identifier: MIT
+//!
+
+use crate::{
+    cfg, jobs::Jobs, limits::RateLimiter, plugins::inline::InlineCache, plugins::req::Http,
+    reporter::Reporter, store::Store, transport::Transport,
+};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+/// State shared by every update handler, built once at startup.
+pub struct AppContext {
+    config_path: PathBuf,
+    config: RwLock<Arc<cfg::Config>>,
+    /// Running without Telegram, see [`AppContext::offline`].
+    offline: bool,
+    /// HTTP client shared by all plugins.
+    pub http: Http,
+    /// The bot's own user ID.
+    pub id: i64,
+    /// The bot's own username, without the leading `@`.
+    pub username: String,
+    /// Cancellable jobs currently running.
+    pub jobs: Jobs,
+    /// Throttles commands per user and chat.
+    pub limits: RateLimiter,
+    /// Results of recent inline queries.
+    pub inline: InlineCache,
+    /// Every message the bot sends goes through here.
+    pub transport: Arc<dyn Transport>,
+    /// Errors and notices for the log chat.
+    pub reporter: Reporter,
+    /// Chat settings and plugin data kept across restarts.
+    pub store: Store,
+}
+
+impl AppContext {
+    pub fn new(
+        config_path: PathBuf,
+        config: Arc<cfg::Config>,
+        transport: Arc<dyn Transport>,
+        store: Store,
+        id: i64,
+        username: impl Into<String>,
+    ) -> Result<Self, reqwest::Error> {
+        Ok(AppContext {
+            config_path,
+            http: Http::new(&config.http)?,
+            config: RwLock::new(config),
+            offline: false,
+            id,
+            username: username.into(),
+            jobs: Jobs::default(),
+            limits: RateLimiter::default(),
+            inline: InlineCache::default(),
+            transport,
+            reporter: Reporter::default(),
+            store,
+        })
+    }
+
+    /// Marks the app as running without Telegram, so reloading the config
+    /// does not require the credentials.
+    pub fn offline(mut self) -> Self {
+        self.offline = true;
+        self
+    }
+
+    /// Returns the currently active config.
+    pub fn config(&self) -> Arc<cfg::Config> {
+        self.config.read().unwrap().clone()
+    }
+
+    /// Re-reads the config file and swaps it in if it is valid.
+    ///
+    /// On failure the running config is left untouched. The previous config is
+    /// returned so callers can tell what changed.
+    pub fn reload(&self) -> Result<Arc<cfg::Config>, cfg::ConfigError> {
+        let config = if self.offline {
+            cfg::Config::read_offline(&self.config_path)?
+        } else {
+            cfg::Config::read(&self.config_path)?
+        };
+        let config = Arc::new(config);
+        let old = std::mem::replace(&mut *self.config.write().unwrap(), config);
+        Ok(old)
+    }
+}
@@ -5,24 +5,839 @@
 //!
 
 //! Import I/O libraries from the stdlib.
-use std::{error::Error, fs::File, io::Read};
+use std::{
+    collections::HashMap,
+    env, fmt,
+    fs::File,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
-#[derive(serde::Deserialize)]
+/// Config file used when `--config` is not given.
+pub const DEFAULT_PATH: &str = "./config.toml";
+
+/// Prefix of the environment variables overriding config fields.
+const ENV_PREFIX: &str = "KNIGHT_BOT_";
+
+/// Looks up an environment variable, so tests can pass their own instead of
+/// changing the process's.
+type Vars<'a> = &'a dyn Fn(&str) -> Option<String>;
 
+#[derive(serde::Deserialize)]
+#[serde(default)]
 pub struct Config {
     /// TG App API
     pub api_id: i32,
     pub api_hash: String,
     /// TG Bot Token
     pub bot_token: String,
-    /// TG Admin User ID
+    /// TG Admin User ID, the bot's owner
     pub admin_id: i64,
+    /// Users allowed to run admin commands
+    pub admins: Vec<i64>,
+    /// Users allowed to run trusted commands
+    pub trusted: Vec<i64>,
+    /// Chat errors, notices and daily summaries are posted to, Bot API style
+    /// (`-100…` for channels); errors go to `admin_id` if unset
+    pub log_chat_id: Option<i64>,
+    /// Where the grammers session is stored
+    pub session_file: String,
+    /// `k.sh` settings
+    pub sh: ShConfig,
+    /// Handling of replies too long for a single message
+    pub output: OutputConfig,
+    /// Client used for upstream APIs
+    pub http: HttpConfig,
+    pub rate_limit: RateLimitConfig,
+    pub outbox: OutboxConfig,
+    pub handlers: HandlersConfig,
+    pub connection: ConnectionConfig,
+    pub reporting: ReportingConfig,
+    pub prefixes: PrefixConfig,
+    pub store: StoreConfig,
+    pub inline: InlineConfig,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(default)]
+pub struct InlineConfig {
+    /// Answer inline queries at all; inline mode also has to be turned on
+    /// with BotFather
+    pub enabled: bool,
+    /// Commands answering inline queries
+    pub plugins: Vec<String>,
+    /// How long results are cached, by the bot and by Telegram
+    pub cache_secs: u64,
+}
+
+impl Default for InlineConfig {
+    fn default() -> Self {
+        InlineConfig {
+            enabled: true,
+            plugins: ["aur", "cat", "magisk", "urb"].map(str::to_owned).to_vec(),
+            cache_secs: 300,
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(default)]
+pub struct StoreConfig {
+    /// JSON file chat settings and plugin data are kept in
+    pub path: String,
+}
+
+impl Default for StoreConfig {
+    fn default() -> Self {
+        StoreConfig {
+            path: "knight-bot.store.json".to_owned(),
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(default)]
+pub struct PrefixConfig {
+    /// Prefixes commands are recognised by in chats without their own
+    pub commands: Vec<String>,
+    /// Prefix of admin commands, the same in every chat
+    pub admin: String,
+}
+
+impl Default for PrefixConfig {
+    fn default() -> Self {
+        PrefixConfig {
+            commands: vec!["/".to_owned()],
+            admin: "k.".to_owned(),
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(default)]
+pub struct ReportingConfig {
+    /// Seconds the same error from the same command is posted only once
+    pub dedup_window_secs: u64,
+    /// Post a summary of commands and errors every 24 hours
+    pub daily_summary: bool,
+}
+
+impl Default for ReportingConfig {
+    fn default() -> Self {
+        ReportingConfig {
+            dedup_window_secs: 600,
+            daily_summary: true,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ConnectionConfig {
+    /// Seconds before the first reconnect attempt, doubled on every further one
+    pub reconnect_initial_secs: u64,
+    /// Longest wait between reconnect attempts
+    pub reconnect_max_secs: u64,
+    /// Seconds between session saves
+    pub save_interval_secs: u64,
+    /// Seconds without updates before the connection is checked
+    pub watchdog_secs: u64,
+}
+
+impl Default for ConnectionConfig {
+    fn default() -> Self {
+        ConnectionConfig {
+            reconnect_initial_secs: 1,
+            reconnect_max_secs: 60,
+            save_interval_secs: 300,
+            watchdog_secs: 900,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, PartialEq)]
+#[serde(default)]
+pub struct HandlersConfig {
+    /// Updates handled at the same time, further ones wait for a free slot
+    pub max_concurrent: usize,
+    /// Seconds given to running handlers to finish on shutdown
+    pub shutdown_timeout_secs: u64,
+}
+
+impl Default for HandlersConfig {
+    fn default() -> Self {
+        HandlersConfig {
+            max_concurrent: 32,
+            shutdown_timeout_secs: 10,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, PartialEq)]
+#[serde(default)]
+pub struct OutboxConfig {
+    /// Longest flood wait slept through, longer ones fail the send
+    pub max_wait_secs: u64,
+    /// Times a message is retried after a flood wait
+    pub retries: u32,
+}
+
+impl Default for OutboxConfig {
+    fn default() -> Self {
+        OutboxConfig {
+            max_wait_secs: 120,
+            retries: 3,
+        }
+    }
+}
+
+/// Up to `burst` commands per `window_secs`, refilled gradually.
+#[derive(serde::Deserialize, Clone)]
+pub struct RateConfig {
+    pub burst: u32,
+    pub window_secs: u64,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    /// Commands one user may send
+    pub user: RateConfig,
+    /// Commands sent in one chat by everyone together
+    pub chat: RateConfig,
+    /// Extra limits per user for expensive commands, by command name
+    pub commands: HashMap<String, RateConfig>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        RateLimitConfig {
+            enabled: true,
+            user: RateConfig {
+                burst: 5,
+                window_secs: 10,
+            },
+            chat: RateConfig {
+                burst: 20,
+                window_secs: 60,
+            },
+            commands: HashMap::from([(
+                "status".to_owned(),
+                RateConfig {
+                    burst: 2,
+                    window_secs: 30,
+                },
+            )]),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, PartialEq)]
+#[serde(default)]
+pub struct HttpConfig {
+    /// Seconds before a request is given up on
+    pub timeout_secs: u64,
+    pub user_agent: String,
+    /// Proxy URL for all requests, e.g. `socks5://127.0.0.1:9050`
+    pub proxy: Option<String>,
+    /// Extra attempts after a network error, 5xx or 429
+    pub retries: u32,
+    /// Delay before the first retry, doubled on every further one
+    pub backoff_ms: u64,
+    pub cache: CacheConfig,
+}
+
+#[derive(serde::Deserialize, PartialEq)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// Most responses kept, the least recently used ones are dropped first
+    pub capacity: usize,
+    /// Seconds a response stays fresh unless `ttl` says otherwise, 0 disables caching
+    pub default_ttl_secs: u64,
+    /// Seconds a response stays fresh, by URL prefix (the longest match wins)
+    pub ttl: HashMap<String, u64>,
+    /// File the cache is kept in across restarts, written along with the session
+    pub persist: Option<String>,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            capacity: 256,
+            default_ttl_secs: 300,
+            ttl: HashMap::from([
+                ("https://raw.githubusercontent.com/".to_owned(), 600),
+                ("https://ipinfo.io/".to_owned(), 86400),
+            ]),
+            persist: None,
+        }
+    }
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        HttpConfig {
+            timeout_secs: 15,
+            user_agent: concat!("knight-bot/", env!("CARGO_PKG_VERSION")).to_owned(),
+            proxy: None,
+            retries: 2,
+            backoff_ms: 500,
+            cache: CacheConfig::default(),
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(default)]
+pub struct ShConfig {
+    /// Seconds before a command is killed
+    pub timeout_secs: u64,
+    /// Seconds between progress updates
+    pub progress_interval_secs: u64,
+    /// Directory commands run in, the bot's own if unset
+    pub workdir: Option<String>,
+    /// Start from an empty environment (keeping only `PATH`)
+    pub clear_env: bool,
+    /// Extra environment variables for commands
+    pub env: HashMap<String, String>,
+}
+
+impl Default for ShConfig {
+    fn default() -> Self {
+        ShConfig {
+            timeout_secs: 60,
+            progress_interval_secs: 3,
+            workdir: None,
+            clear_env: false,
+            env: HashMap::new(),
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            api_id: 0,
+            api_hash: String::new(),
+            bot_token: String::new(),
+            admin_id: 0,
+            admins: Vec::new(),
+            trusted: Vec::new(),
+            log_chat_id: None,
+            session_file: "knight-bot.session".to_owned(),
+            sh: ShConfig::default(),
+            output: OutputConfig::default(),
+            http: HttpConfig::default(),
+            rate_limit: RateLimitConfig::default(),
+            outbox: OutboxConfig::default(),
+            handlers: HandlersConfig::default(),
+            connection: ConnectionConfig::default(),
+            reporting: ReportingConfig::default(),
+            prefixes: PrefixConfig::default(),
+            store: StoreConfig::default(),
+            inline: InlineConfig::default(),
+        }
+    }
+}
+
+/// How output longer than [`OutputConfig::max_length`] is delivered.
+#[derive(serde::Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputMode {
+    /// Several messages split on line boundaries
+    Split,
+    /// A `.txt` document
+    Document,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(default)]
+pub struct OutputConfig {
+    /// Longest reply sent as a single message
+    pub max_length: usize,
+    pub mode: OutputMode,
+    /// Messages a split reply may take before falling back to a document
+    pub max_messages: usize,
+    /// Also paste long output to rustbin and link it
+    pub paste: bool,
 }
 
+impl Default for OutputConfig {
+    fn default() -> Self {
+        OutputConfig {
+            max_length: 4000,
+            mode: OutputMode::Split,
+            max_messages: 5,
+            paste: false,
+        }
+    }
+}
+
+/// What a user is allowed to do, from least to most privileged.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    User,
+    Trusted,
+    /// An admin of the chat the command was sent in, as set in Telegram
+    ChatAdmin,
+    Admin,
+    Owner,
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Role::User => "user",
+            Role::Trusted => "trusted user",
+            Role::ChatAdmin => "chat admin",
+            Role::Admin => "admin",
+            Role::Owner => "owner",
+        })
+    }
+}
+
+/// Everything that is wrong with a config, collected in one go.
+#[derive(Debug)]
+pub struct ConfigError {
+    pub path: PathBuf,
+    pub problems: Vec<String>,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid config ({}):", self.path.display())?;
+        for problem in &self.problems {
+            write!(f, "\n  - {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
 impl Config {
-    pub fn read() -> Result<Self, Box<dyn Error>> {
-        let mut str = String::new();
-        File::open("./config.toml")?.read_to_string(&mut str)?;
-        Ok(toml::from_str(&str)?)
+    /// Reads the config at `path`, applies `KNIGHT_BOT_*` overrides and validates it.
+    ///
+    /// A missing file is fine as long as the environment provides every
+    /// required field.
+    pub fn read(path: &Path) -> Result<Self, ConfigError> {
+        Config::load(path, true, &|name| env::var(name).ok())
+    }
+
+    /// Like [`Config::read`], but without requiring the Telegram credentials,
+    /// for `--repl`.
+    pub fn read_offline(path: &Path) -> Result<Self, ConfigError> {
+        Config::load(path, false, &|name| env::var(name).ok())
+    }
+
+    fn load(path: &Path, credentials: bool, vars: Vars) -> Result<Self, ConfigError> {
+        let mut problems = Vec::new();
+
+        let mut config = match read_file(path) {
+            Ok(Some(str)) => match toml::from_str(&str) {
+                Ok(config) => config,
+                Err(e) => {
+                    problems.push(format!("cannot parse {}: {}", path.display(), e));
+                    Config::default()
+                }
+            },
+            Ok(None) => {
+                log::warn!(
+                    "{} not found, relying on environment variables",
+                    path.display()
+                );
+                Config::default()
+            }
+            Err(e) => {
+                problems.push(format!("cannot read {}: {}", path.display(), e));
+                Config::default()
+            }
+        };
+
+        config.apply_env(vars, &mut problems);
+        if credentials {
+            config.validate_credentials(&mut problems);
+        }
+        config.validate(&mut problems);
+
+        if problems.is_empty() {
+            Ok(config)
+        } else {
+            Err(ConfigError {
+                path: path.to_owned(),
+                problems,
+            })
+        }
+    }
+
+    /// Returns the role of the given user.
+    pub fn role_of(&self, user_id: i64) -> Role {
+        if user_id == self.admin_id {
+            Role::Owner
+        } else if self.admins.contains(&user_id) {
+            Role::Admin
+        } else if self.trusted.contains(&user_id) {
+            Role::Trusted
+        } else {
+            Role::User
+        }
+    }
+
+    fn apply_env(&mut self, vars: Vars, problems: &mut Vec<String>) {
+        env_override(vars, "API_ID", &mut self.api_id, problems);
+        env_override(vars, "API_HASH", &mut self.api_hash, problems);
+        env_override(vars, "BOT_TOKEN", &mut self.bot_token, problems);
+        env_override(vars, "ADMIN_ID", &mut self.admin_id, problems);
+        env_list_override(vars, "ADMINS", &mut self.admins, problems);
+        env_list_override(vars, "TRUSTED", &mut self.trusted, problems);
+        env_override(vars, "SESSION_FILE", &mut self.session_file, problems);
+        env_option_override(vars, "LOG_CHAT_ID", &mut self.log_chat_id, problems);
+    }
+
+    fn validate_credentials(&self, problems: &mut Vec<String>) {
+        if self.api_id == 0 {
+            problems.push("api_id is missing".to_owned());
+        } else if self.api_id < 0 {
+            problems.push("api_id must be a positive number".to_owned());
+        }
+
+        if self.api_hash.is_empty() {
+            problems.push("api_hash is missing".to_owned());
+        } else if self.api_hash.len() != 32 || !self.api_hash.chars().all(|c| c.is_ascii_hexdigit())
+        {
+            problems.push("api_hash must be the 32 hex characters from my.telegram.org".to_owned());
+        }
+
+        if self.bot_token.is_empty() {
+            problems.push("bot_token is missing".to_owned());
+        } else if !valid_token(&self.bot_token) {
+            problems.push(
+                "bot_token must look like `123456:ABC-DEF...` (as given by @BotFather)".to_owned(),
+            );
+        }
+
+        if self.admin_id == 0 {
+            problems.push("admin_id is missing".to_owned());
+        }
+    }
+
+    fn validate(&self, problems: &mut Vec<String>) {
+        if self.log_chat_id == Some(0) {
+            problems.push("log_chat_id must not be 0".to_owned());
+        }
+        if self.admins.contains(&0) {
+            problems.push("admins must not contain 0".to_owned());
+        }
+        if self.trusted.contains(&0) {
+            problems.push("trusted must not contain 0".to_owned());
+        }
+
+        if self.session_file.trim().is_empty() {
+            problems.push("session_file must not be empty".to_owned());
+        }
+
+        if self.sh.timeout_secs == 0 {
+            problems.push("sh.timeout_secs must be at least 1".to_owned());
+        }
+        if self.sh.progress_interval_secs == 0 {
+            problems.push("sh.progress_interval_secs must be at least 1".to_owned());
+        }
+        if let Some(dir) = &self.sh.workdir {
+            if !Path::new(dir).is_dir() {
+                problems.push(format!("sh.workdir `{}` is not a directory", dir));
+            }
+        }
+
+        if !(100..=4096).contains(&self.output.max_length) {
+            problems.push("output.max_length must be between 100 and 4096".to_owned());
+        }
+        if self.output.max_messages == 0 {
+            problems.push("output.max_messages must be at least 1".to_owned());
+        }
+
+        if self.http.timeout_secs == 0 {
+            problems.push("http.timeout_secs must be at least 1".to_owned());
+        }
+        if self.http.retries > 10 {
+            problems.push("http.retries must be at most 10".to_owned());
+        }
+        if let Some(proxy) = &self.http.proxy {
+            if reqwest::Proxy::all(proxy).is_err() {
+                problems.push(format!("http.proxy `{}` is not a valid proxy URL", proxy));
+            }
+        }
+        if !(1..=10_000).contains(&self.handlers.max_concurrent) {
+            problems.push("handlers.max_concurrent must be between 1 and 10000".to_owned());
+        }
+
+        let connection = &self.connection;
+        if connection.reconnect_initial_secs == 0 {
+            problems.push("connection.reconnect_initial_secs must be at least 1".to_owned());
+        }
+        if connection.reconnect_max_secs < connection.reconnect_initial_secs {
+            problems.push(
+                "connection.reconnect_max_secs must not be less than reconnect_initial_secs"
+                    .to_owned(),
+            );
+        }
+        if connection.save_interval_secs == 0 {
+            problems.push("connection.save_interval_secs must be at least 1".to_owned());
+        }
+        if connection.watchdog_secs < 60 {
+            problems.push("connection.watchdog_secs must be at least 60".to_owned());
+        }
+
+        let rates = [
+            ("rate_limit.user".to_owned(), &self.rate_limit.user),
+            ("rate_limit.chat".to_owned(), &self.rate_limit.chat),
+        ]
+        .into_iter()
+        .chain(
+            self.rate_limit
+                .commands
+                .iter()
+                .map(|(name, rate)| (format!("rate_limit.commands.{}", name), rate)),
+        );
+        for (name, rate) in rates {
+            if rate.burst == 0 || rate.window_secs == 0 {
+                problems.push(format!(
+                    "{}: burst and window_secs must be at least 1",
+                    name
+                ));
+            }
+        }
+
+        let prefixes = &self.prefixes;
+        if prefixes.commands.is_empty() {
+            problems.push("prefixes.commands must not be empty".to_owned());
+        }
+        for prefix in prefixes.commands.iter().chain([&prefixes.admin]) {
+            if let Err(e) = crate::prefixes::check(prefix) {
+                problems.push(format!("prefixes: {}", e));
+            }
+        }
+        if let Some(prefix) = prefixes
+            .commands
+            .iter()
+            .find(|prefix| !prefixes.admin.is_empty() && prefix.starts_with(&prefixes.admin))
+        {
+            problems.push(format!(
+                "prefixes.commands: `{}` is shadowed by the admin prefix `{}`",
+                prefix, prefixes.admin
+            ));
+        }
+        if self.store.path.trim().is_empty() {
+            problems.push("store.path must not be empty".to_owned());
+        }
+        for name in &self.inline.plugins {
+            match crate::plugins::find(name, false) {
+                Some(plugin) if plugin.inline().is_some() && plugin.role() == Role::User => {}
+                Some(_) => problems.push(format!(
+                    "inline.plugins: `{}` can't answer inline queries",
+                    name
+                )),
+                None => problems.push(format!("inline.plugins: no command `{}`", name)),
+            }
+        }
+
+        if self.http.cache.capacity == 0 {
+            problems.push("http.cache.capacity must be at least 1".to_owned());
+        }
+        if let Some(persist) = &self.http.cache.persist {
+            if persist.trim().is_empty() {
+                problems.push("http.cache.persist must not be empty".to_owned());
+            }
+        }
+    }
+}
+
+/// Returns `Ok(None)` if the file does not exist.
+fn read_file(path: &Path) -> io::Result<Option<String>> {
+    let mut str = String::new();
+    match File::open(path) {
+        Ok(mut file) => {
+            file.read_to_string(&mut str)?;
+            Ok(Some(str))
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn env_override<T: FromStr>(vars: Vars, field: &str, value: &mut T, problems: &mut Vec<String>) {
+    let name = format!("{}{}", ENV_PREFIX, field);
+    if let Some(raw) = vars(&name) {
+        match raw.trim().parse() {
+            Ok(parsed) => *value = parsed,
+            Err(_) => problems.push(format!("{} has an invalid value `{}`", name, raw)),
+        }
+    }
+}
+
+/// Like [`env_override`] for optional settings, an empty variable unsets them.
+fn env_option_override<T: FromStr>(
+    vars: Vars,
+    field: &str,
+    value: &mut Option<T>,
+    problems: &mut Vec<String>,
+) {
+    let name = format!("{}{}", ENV_PREFIX, field);
+    if let Some(raw) = vars(&name) {
+        if raw.trim().is_empty() {
+            *value = None;
+            return;
+        }
+        match raw.trim().parse() {
+            Ok(parsed) => *value = Some(parsed),
+            Err(_) => problems.push(format!("{} has an invalid value `{}`", name, raw)),
+        }
+    }
+}
+
+/// Like [`env_override`] for comma-separated lists.
+fn env_list_override<T: FromStr>(
+    vars: Vars,
+    field: &str,
+    value: &mut Vec<T>,
+    problems: &mut Vec<String>,
+) {
+    let name = format!("{}{}", ENV_PREFIX, field);
+    if let Some(raw) = vars(&name) {
+        let parsed = raw
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(str::parse)
+            .collect::<Result<Vec<_>, _>>();
+        match parsed {
+            Ok(parsed) => *value = parsed,
+            Err(_) => problems.push(format!("{} has an invalid value `{}`", name, raw)),
+        }
+    }
+}
+
+fn valid_token(token: &str) -> bool {
+    match token.split_once(':') {
+        Some((id, secret)) => {
+            !id.is_empty() && id.chars().all(|c| c.is_ascii_digit()) && !secret.is_empty()
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// A config file under the temp dir, removed again when dropped.
+    struct TempConfig(PathBuf);
+
+    impl TempConfig {
+        fn new(name: &str, toml: &str) -> Self {
+            let path =
+                env::temp_dir().join(format!("knight-bot-{}-{}.toml", name, std::process::id()));
+            fs::write(&path, toml).unwrap();
+            TempConfig(path)
+        }
+    }
+
+    impl Drop for TempConfig {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn every_problem_is_reported() {
+        let file = TempConfig::new(
+            "invalid",
+            r#"
+api_id = -5
+api_hash = "abc"
+bot_token = "nocolon"
+trusted = [0]
+
+[sh]
+timeout_secs = 0
+
+[output]
+max_length = 10
+
+[prefixes]
+commands = []
+
+[rate_limit.user]
+burst = 0
+window_secs = 10
+
+[inline]
+plugins = ["nosuch", "ping"]
+"#,
+        );
+        let problems = match Config::load(&file.0, true, &|_| None) {
+            Ok(_) => panic!("invalid config was accepted"),
+            Err(e) => e.problems,
+        };
+        for expected in [
+            "api_id must be a positive number",
+            "api_hash must be the 32 hex characters from my.telegram.org",
+            "bot_token must look like `123456:ABC-DEF...` (as given by @BotFather)",
+            "trusted must not contain 0",
+            "sh.timeout_secs must be at least 1",
+            "output.max_length must be between 100 and 4096",
+            "prefixes.commands must not be empty",
+            "rate_limit.user: burst and window_secs must be at least 1",
+            "inline.plugins: no command `nosuch`",
+            "inline.plugins: `ping` can't answer inline queries",
+        ] {
+            assert!(
+                problems.iter().any(|problem| problem == expected),
+                "`{}` not in {:?}",
+                expected,
+                problems
+            );
+        }
+    }
+
+    #[test]
+    fn unparsable_files_are_a_problem() {
+        let file = TempConfig::new("unparsable", "api_id = \"twelve\"");
+        match Config::load(&file.0, false, &|_| None) {
+            Ok(_) => panic!("unparsable config was accepted"),
+            Err(e) => assert!(e.problems[0].starts_with("cannot parse")),
+        }
+    }
+
+    #[test]
+    fn environment_overrides_the_file() {
+        let file = TempConfig::new(
+            "env",
+            "admin_id = 7\nadmins = [1]\nsession_file = \"file.session\"\n",
+        );
+        let env = HashMap::from([
+            ("KNIGHT_BOT_ADMINS", "5, 6"),
+            ("KNIGHT_BOT_SESSION_FILE", "env.session"),
+        ]);
+        let vars = |name: &str| env.get(name).map(|value| value.to_string());
+        let config = match Config::load(&file.0, false, &vars) {
+            Ok(config) => config,
+            Err(e) => panic!("{}", e),
+        };
+        assert_eq!(config.admins, [5, 6]);
+        assert_eq!(config.session_file, "env.session");
+        assert_eq!(config.admin_id, 7);
+
+        let env = HashMap::from([("KNIGHT_BOT_LOG_CHAT_ID", "soon")]);
+        let vars = |name: &str| env.get(name).map(|value| value.to_string());
+        match Config::load(&file.0, false, &vars) {
+            Ok(_) => panic!("invalid override was accepted"),
+            Err(e) => assert_eq!(
+                e.problems,
+                ["KNIGHT_BOT_LOG_CHAT_ID has an invalid value `soon`"]
+            ),
+        }
     }
 }
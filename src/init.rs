@@ -4,57 +4,385 @@
 //! SPDX-License-Identifier: MIT
 //!
 
-use crate::{cfg, plugins};
+use crate::transport::telegram::Telegram;
+use crate::{app::AppContext, cfg, menu, outbox::Outbox, plugins, store::Store};
 use grammers_client::{Client, Config, InitParams};
 use grammers_session::Session;
 use log;
-use std::sync::Arc;
-use tokio::task;
+use std::{future::Future, path::PathBuf, pin::Pin, sync::Arc, time::Duration};
+use tokio::{sync::Semaphore, task, time};
 
 type Result = std::result::Result<(), Box<dyn std::error::Error>>;
 
-const SESSION_FILE: &str = "knight-bot.session";
+/// Log target for everything about the connection to Telegram.
+const CONNECTION: &str = "knight_bot::connection";
 
-pub async fn async_main() -> Result {
-    let config = Arc::new(cfg::Config::read().expect("cannot read the config"));
-    let api_id = config.clone().api_id;
-    let api_hash = &config.api_hash;
-    let token = &config.bot_token;
+/// How long the watchdog waits for an answer when probing the connection.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(30);
 
-    log::info!("Connecting to Telegram...");
+pub async fn async_main(config_path: PathBuf) -> Result {
+    let config = Arc::new(cfg::Config::read(&config_path)?);
+    let session_file = &config.session_file;
+    let store = Store::open(&config.store.path)
+        .map_err(|e| format!("Failed to open store {}: {}", config.store.path, e))?;
+
+    let mut client = connect(&config).await?;
+
+    let me = client.get_me().await?;
+    let telegram = Arc::new(Telegram::new(client.clone(), Outbox::new(&config.outbox)));
+    let app = Arc::new(AppContext::new(
+        config_path,
+        config.clone(),
+        telegram.clone(),
+        store,
+        me.id(),
+        me.username().unwrap_or_default(),
+    )?);
+    log::info!("Signed in as @{}", app.username);
+    match menu::publish(&app, &client, &[]).await {
+        Ok(published) => log::info!(
+            "Published {} commands to the command menu",
+            published.public
+        ),
+        Err(e) => log::warn!("Failed to publish the command menu: {}", e),
+    }
+    app.reporter
+        .notice(&app, &format!("started as @{}", app.username))
+        .await;
+
+    log::info!("Waiting for messages...");
+
+    // Every running handler holds a permit, so once all of them are back
+    // nothing is running anymore.
+    let max_concurrent = config.handlers.max_concurrent;
+    let slots = Arc::new(Semaphore::new(max_concurrent));
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
+
+    let connection = &config.connection;
+    let watchdog = Duration::from_secs(connection.watchdog_secs);
+    let day = Duration::from_secs(24 * 60 * 60);
+    let mut timers = Timers::new(
+        Duration::from_secs(connection.save_interval_secs),
+        day,
+        watchdog,
+    );
+
+    loop {
+        let update = match timers.next(&mut shutdown, client.next_update()).await {
+            Wake::Shutdown => break,
+            Wake::Save => {
+                save_session(&client, session_file);
+                app.http.cache().persist().await;
+                continue;
+            }
+            Wake::Summary => {
+                if app.config().reporting.daily_summary {
+                    app.reporter.summary(&app).await;
+                }
+                continue;
+            }
+            Wake::Update(Ok(Some(update))) => Some(update),
+            Wake::Update(Ok(None)) => break,
+            Wake::Update(Err(e)) => {
+                log::warn!(target: CONNECTION, "Update stream failed: {}", e);
+                None
+            }
+            Wake::Stalled => {
+                // Quiet chats are normal, so only reconnect if Telegram does
+                // not answer either.
+                if responsive(client.get_me(), watchdog, PROBE_TIMEOUT).await {
+                    continue;
+                }
+                None
+            }
+        };
+        let update = match update {
+            Some(update) => update,
+            None => {
+                let reconnected = tokio::select! {
+                    _ = &mut shutdown => break,
+                    client = reconnect(&config, &client) => client,
+                };
+                telegram.reconnected(reconnected.clone());
+                client = reconnected;
+                continue;
+            }
+        };
+        let permit = tokio::select! {
+            _ = &mut shutdown => break,
+            permit = slots.clone().acquire_owned() => permit?,
+        };
+
+        let app = app.clone();
+        task::spawn(async move {
+            match plugins::handle_update(app, update).await {
+                Ok(_) => {}
+                Err(e) => log::error!("Error handling updates!: {}", e),
+            }
+            drop(permit);
+        });
+    }
+
+    log::info!("Shutting down...");
+    let cancelled = app.jobs.cancel_all();
+    if cancelled > 0 {
+        log::info!("Cancelled {} running job(s)", cancelled);
+    }
+    let deadline = Duration::from_secs(config.handlers.shutdown_timeout_secs);
+    match time::timeout(deadline, slots.acquire_many(max_concurrent as u32)).await {
+        Ok(_) => log::info!("All handlers finished"),
+        Err(_) => log::warn!(
+            "{} handler(s) still running after {:?}, dropping them",
+            max_concurrent - slots.available_permits(),
+            deadline
+        ),
+    }
+
+    app.reporter.notice(&app, "shutting down").await;
+    app.http.cache().persist().await;
+    client.session().save_to_file(session_file)?;
+    log::info!("Session saved, bye!");
+    Ok(())
+}
+
+/// What [`Timers::next`] woke up for.
+enum Wake<U> {
+    Shutdown,
+    /// Time to save the session and the response cache
+    Save,
+    /// Time for the daily summary
+    Summary,
+    Update(U),
+    /// No update arrived for the whole watchdog period
+    Stalled,
+}
+
+/// The timers the main loop wakes up for besides updates.
+struct Timers {
+    save: time::Interval,
+    summary: time::Interval,
+    /// Deadline for the next update, only pushed back when one arrives so
+    /// the other timers firing can't hide a stalled connection
+    stall: Pin<Box<time::Sleep>>,
+    watchdog: Duration,
+}
+
+impl Timers {
+    fn new(save: Duration, summary: Duration, watchdog: Duration) -> Self {
+        let mut save = time::interval(save);
+        save.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+        Timers {
+            save,
+            summary: time::interval_at(time::Instant::now() + summary, summary),
+            stall: Box::pin(time::sleep(watchdog)),
+            watchdog,
+        }
+    }
+
+    /// Waits for `shutdown`, `update` or the next timer, whichever comes first.
+    async fn next<U>(
+        &mut self,
+        shutdown: impl Future<Output = ()>,
+        update: impl Future<Output = U>,
+    ) -> Wake<U> {
+        let wake = tokio::select! {
+            _ = shutdown => Wake::Shutdown,
+            _ = self.save.tick() => Wake::Save,
+            _ = self.summary.tick() => Wake::Summary,
+            update = update => Wake::Update(update),
+            _ = self.stall.as_mut() => Wake::Stalled,
+        };
+        // After a stall the connection is checked or replaced, either way
+        // the next check is a whole period away.
+        if let Wake::Update(_) | Wake::Stalled = wake {
+            let deadline = time::Instant::now() + self.watchdog;
+            self.stall.as_mut().reset(deadline);
+        }
+        wake
+    }
+}
+
+/// Connects to Telegram with the saved session, signing in if needed.
+async fn connect(config: &cfg::Config) -> std::result::Result<Client, Box<dyn std::error::Error>> {
+    log::info!(target: CONNECTION, "Connecting to Telegram...");
     let client = Client::connect(Config {
-        session: Session::load_file_or_create(SESSION_FILE)?,
-        api_id,
-        api_hash: api_hash.clone(),
+        session: Session::load_file_or_create(&config.session_file)?,
+        api_id: config.api_id,
+        api_hash: config.api_hash.clone(),
         params: InitParams {
             ..Default::default()
         },
     })
     .await?;
-    log::info!("Connected!");
+    log::info!(target: CONNECTION, "Connected!");
 
     if !client.is_authorized().await? {
         log::info!("Signing in...");
-        client.bot_sign_in(&token, api_id, &api_hash).await?;
-        client.session().save_to_file(SESSION_FILE)?;
+        client
+            .bot_sign_in(&config.bot_token, config.api_id, &config.api_hash)
+            .await?;
+        client.session().save_to_file(&config.session_file)?;
         log::info!("Signed in!");
     }
+    Ok(client)
+}
 
-    log::info!("Waiting for messages...");
+/// Connects again after the update stream broke, backing off between attempts.
+async fn reconnect(config: &cfg::Config, old: &Client) -> Client {
+    save_session(old, &config.session_file);
+    for (attempt, delay) in (1..).zip(backoff(&config.connection)) {
+        match connect(config).await {
+            Ok(client) => {
+                log::info!(target: CONNECTION, "Reconnected after {} attempt(s)", attempt);
+                return client;
+            }
+            Err(e) => log::warn!(
+                target: CONNECTION,
+                "Reconnect attempt {} failed, retrying in {:?}: {}",
+                attempt,
+                delay,
+                e
+            ),
+        }
+        time::sleep(delay).await;
+    }
+    unreachable!("backoff never ends")
+}
 
-    while let Some(update) = tokio::select! {
-        _ = tokio::signal::ctrl_c() => Ok(None),
-        result = client.next_update() => result,
-    }? {
-        let handle = client.clone();
-        task::spawn(async move {
-            match plugins::handle_update(handle, update).await {
-                Ok(_) => {}
-                Err(e) => log::error!("Error handling updates!: {}", e),
+/// Waits between reconnect attempts, doubling up to `reconnect_max_secs`.
+fn backoff(connection: &cfg::ConnectionConfig) -> impl Iterator<Item = Duration> {
+    let max = Duration::from_secs(connection.reconnect_max_secs);
+    let initial = Duration::from_secs(connection.reconnect_initial_secs);
+    std::iter::successors(Some(initial.min(max)), move |delay| {
+        Some((*delay * 2).min(max))
+    })
+}
+
+/// Whether `probe`, sent after `watchdog` without updates, got an answer within `timeout`.
+async fn responsive<T, E: std::fmt::Display>(
+    probe: impl std::future::Future<Output = std::result::Result<T, E>>,
+    watchdog: Duration,
+    timeout: Duration,
+) -> bool {
+    match time::timeout(timeout, probe).await {
+        Ok(Ok(_)) => {
+            log::debug!(
+                target: CONNECTION,
+                "No updates for {:?}, connection is fine",
+                watchdog
+            );
+            true
+        }
+        Ok(Err(e)) => {
+            log::warn!(
+                target: CONNECTION,
+                "No updates for {:?} and probe failed: {}",
+                watchdog,
+                e
+            );
+            false
+        }
+        Err(_) => {
+            log::warn!(
+                target: CONNECTION,
+                "No updates for {:?} and probe timed out after {:?}",
+                watchdog,
+                timeout
+            );
+            false
+        }
+    }
+}
+
+/// Saves the session, logging instead of failing so a full disk does not take the bot down.
+fn save_session(client: &Client, session_file: &str) {
+    match client.session().save_to_file(session_file) {
+        Ok(()) => log::debug!(target: CONNECTION, "Session saved to {}", session_file),
+        Err(e) => {
+            log::warn!(target: CONNECTION, "Failed to save session to {}: {}", session_file, e)
+        }
+    }
+}
+
+/// Resolves on Ctrl-C, or on SIGTERM (as sent by `docker stop`) on Unix.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = sigterm.recv() => {}
+                }
+                return;
             }
-        });
+            Err(e) => log::warn!("Can't listen for SIGTERM: {}", e),
+        }
     }
+    let _ = tokio::signal::ctrl_c().await;
+}
 
-    client.session().save_to_file(SESSION_FILE)?;
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_the_max() {
+        let connection = cfg::ConnectionConfig {
+            reconnect_initial_secs: 1,
+            reconnect_max_secs: 10,
+            ..Default::default()
+        };
+        let delays = backoff(&connection)
+            .take(6)
+            .map(|delay| delay.as_secs())
+            .collect::<Vec<_>>();
+        assert_eq!(delays, [1, 2, 4, 8, 10, 10]);
+    }
+
+    #[tokio::test]
+    async fn other_timers_do_not_hide_a_stall() {
+        let ms = Duration::from_millis;
+        let mut timers = Timers::new(ms(10), ms(15), ms(100));
+        let mut ticks = 0;
+        loop {
+            let wake = timers
+                .next(std::future::pending(), std::future::pending::<()>())
+                .await;
+            match wake {
+                Wake::Save | Wake::Summary => ticks += 1,
+                Wake::Stalled => break,
+                Wake::Shutdown | Wake::Update(_) => unreachable!(),
+            }
+        }
+        assert!(ticks >= 5, "stalled after only {} ticks", ticks);
+    }
+
+    #[tokio::test]
+    async fn updates_push_the_stall_back() {
+        let ms = Duration::from_millis;
+        let mut timers = Timers::new(ms(1000), ms(1000), ms(50));
+        // The save interval ticks right away.
+        timers
+            .next(std::future::pending(), std::future::pending::<()>())
+            .await;
+        for _ in 0..10 {
+            let wake = timers
+                .next(std::future::pending(), time::sleep(ms(20)))
+                .await;
+            assert!(matches!(wake, Wake::Update(())));
+        }
+    }
+
+    #[tokio::test]
+    async fn stalled_connections_are_noticed() {
+        let watchdog = Duration::from_secs(900);
+        let timeout = Duration::from_millis(10);
+        assert!(responsive(async { Ok::<_, String>(()) }, watchdog, timeout).await);
+        assert!(!responsive(async { Err::<(), _>("closed") }, watchdog, timeout).await);
+        let hung = std::future::pending::<std::result::Result<(), String>>();
+        assert!(!responsive(hung, watchdog, timeout).await);
+    }
 }
@@ -0,0 +1,60 @@
+//!
+//! Copyright (C) 2023-2025 cyberknight777
+//!
+//! SPDX-License-Identifier: MIT
+//!
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::sync::oneshot;
+
+/// Identifies a job by the chat it runs in and the message showing its progress.
+pub type JobId = (i64, i32);
+
+/// Long-running jobs (such as `k.sh` commands) that can be cancelled.
+#[derive(Default)]
+pub struct Jobs {
+    running: Mutex<HashMap<JobId, oneshot::Sender<()>>>,
+}
+
+impl Jobs {
+    /// Registers a job; the returned receiver fires when it gets cancelled.
+    pub fn start(&self, id: JobId) -> oneshot::Receiver<()> {
+        let (tx, rx) = oneshot::channel();
+        self.running.lock().unwrap().insert(id, tx);
+        rx
+    }
+
+    /// Forgets about a job that has ended.
+    pub fn finish(&self, id: JobId) {
+        self.running.lock().unwrap().remove(&id);
+    }
+
+    /// Cancels one job, or every job in `chat` if `msg` is `None`.
+    ///
+    /// Returns how many jobs were cancelled.
+    pub fn cancel(&self, chat: i64, msg: Option<i32>) -> usize {
+        let mut running = self.running.lock().unwrap();
+        let ids = running
+            .keys()
+            .filter(|(c, m)| *c == chat && msg.map_or(true, |msg| *m == msg))
+            .copied()
+            .collect::<Vec<_>>();
+        for id in &ids {
+            if let Some(tx) = running.remove(id) {
+                let _ = tx.send(());
+            }
+        }
+        ids.len()
+    }
+
+    /// Cancels every job, e.g. on shutdown.
+    pub fn cancel_all(&self) -> usize {
+        let mut running = self.running.lock().unwrap();
+        let count = running.len();
+        for (_, tx) in running.drain() {
+            let _ = tx.send(());
+        }
+        count
+    }
+}
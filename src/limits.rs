@@ -0,0 +1,288 @@
+//!
+//! Copyright (C) 2023-2025 cyberknight777
+//!
+//! SPDX-License-Identifier: MIT
+//!
+
+use crate::cfg::{RateConfig, RateLimitConfig, Role};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Buckets idle for this long are full again and can be forgotten.
+const IDLE: Duration = Duration::from_secs(3600);
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Key {
+    User(i64),
+    Chat(i64),
+    Command(i64, &'static str),
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    /// Until when the user has already been told to slow down
+    notified_until: Option<Instant>,
+}
+
+impl Bucket {
+    fn refill(&mut self, rate: &RateConfig, now: Instant) {
+        let per_sec = rate.burst as f64 / rate.window_secs as f64;
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * per_sec).min(rate.burst as f64);
+        self.updated = now;
+    }
+
+    /// How long until a token is available again.
+    fn wait(&self, rate: &RateConfig) -> Duration {
+        let per_sec = rate.burst as f64 / rate.window_secs as f64;
+        Duration::from_secs_f64(((1.0 - self.tokens) / per_sec).max(0.0))
+    }
+}
+
+/// Whether a command may run.
+pub enum Verdict {
+    Allow,
+    /// Over the limit; `notify` is set the first time in a window so the
+    /// cooldown reply is not itself spammed.
+    Limited {
+        retry_after: Duration,
+        notify: bool,
+    },
+}
+
+/// Token buckets per user, per chat and per user and command.
+#[derive(Default)]
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<Key, Bucket>>,
+}
+
+impl RateLimiter {
+    /// Takes a token from every bucket `command` falls under, or none if any is empty.
+    ///
+    /// `chat` is `None` for inline queries, which are not sent in any chat.
+    /// Admins and the owner are never limited.
+    pub fn check(
+        &self,
+        config: &RateLimitConfig,
+        role: Role,
+        user: i64,
+        chat: Option<i64>,
+        command: &'static str,
+    ) -> Verdict {
+        self.check_at(config, role, user, chat, command, Instant::now())
+    }
+
+    fn check_at(
+        &self,
+        config: &RateLimitConfig,
+        role: Role,
+        user: i64,
+        chat: Option<i64>,
+        command: &'static str,
+        now: Instant,
+    ) -> Verdict {
+        if !config.enabled || role >= Role::Admin {
+            return Verdict::Allow;
+        }
+        let mut limits = vec![(Key::User(user), &config.user)];
+        if let Some(chat) = chat {
+            limits.push((Key::Chat(chat), &config.chat));
+        }
+        if let Some(rate) = config.commands.get(command) {
+            limits.push((Key::Command(user, command), rate));
+        }
+
+        let mut buckets = self.buckets.lock().unwrap();
+        buckets.retain(|_, bucket| now.duration_since(bucket.updated) < IDLE);
+
+        let mut retry_after = Duration::ZERO;
+        for (key, rate) in &limits {
+            let bucket = buckets.entry(key.clone()).or_insert(Bucket {
+                tokens: rate.burst as f64,
+                updated: now,
+                notified_until: None,
+            });
+            bucket.refill(rate, now);
+            if bucket.tokens < 1.0 {
+                retry_after = retry_after.max(bucket.wait(rate));
+            }
+        }
+
+        if retry_after.is_zero() {
+            for (key, _) in &limits {
+                if let Some(bucket) = buckets.get_mut(key) {
+                    bucket.tokens -= 1.0;
+                }
+            }
+            return Verdict::Allow;
+        }
+
+        // Only the user's own bucket remembers the cooldown reply, so one
+        // user hitting the chat limit does not silence it for everyone else.
+        let notified = buckets.get_mut(&Key::User(user)).unwrap();
+        let notify = notified.notified_until.map_or(true, |until| now >= until);
+        if notify {
+            notified.notified_until = Some(now + retry_after);
+        }
+        Verdict::Limited {
+            retry_after,
+            notify,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USER: i64 = 1;
+    const CHAT: i64 = -1;
+
+    fn config() -> RateLimitConfig {
+        RateLimitConfig {
+            enabled: true,
+            user: RateConfig {
+                burst: 2,
+                window_secs: 10,
+            },
+            chat: RateConfig {
+                burst: 100,
+                window_secs: 10,
+            },
+            commands: HashMap::from([(
+                "status".to_owned(),
+                RateConfig {
+                    burst: 1,
+                    window_secs: 30,
+                },
+            )]),
+        }
+    }
+
+    fn allowed(verdict: Verdict) -> bool {
+        matches!(verdict, Verdict::Allow)
+    }
+
+    #[test]
+    fn burst_runs_out_and_refills() {
+        let limiter = RateLimiter::default();
+        let config = config();
+        let start = Instant::now();
+        let check = |secs: u64| {
+            limiter.check_at(
+                &config,
+                Role::User,
+                USER,
+                Some(CHAT),
+                "ping",
+                start + Duration::from_secs(secs),
+            )
+        };
+
+        assert!(allowed(check(0)));
+        assert!(allowed(check(0)));
+        match check(0) {
+            Verdict::Limited {
+                retry_after,
+                notify,
+            } => {
+                assert_eq!(retry_after, Duration::from_secs(5));
+                assert!(notify);
+            }
+            Verdict::Allow => panic!("third command in a burst of two was allowed"),
+        }
+        // Told once per cooldown, not on every further attempt.
+        assert!(matches!(check(1), Verdict::Limited { notify: false, .. }));
+
+        // One token comes back every five seconds.
+        assert!(allowed(check(6)));
+        assert!(!allowed(check(6)));
+        assert!(allowed(check(60)));
+        assert!(allowed(check(60)));
+    }
+
+    #[test]
+    fn commands_have_their_own_limits() {
+        let limiter = RateLimiter::default();
+        let config = config();
+        let now = Instant::now();
+        let check = |user: i64, command: &'static str| {
+            limiter.check_at(&config, Role::User, user, Some(CHAT), command, now)
+        };
+
+        assert!(allowed(check(USER, "status")));
+        assert!(!allowed(check(USER, "status")));
+        // The user's own bucket still has a token for other commands.
+        assert!(allowed(check(USER, "ping")));
+        // And the limit is per user.
+        assert!(allowed(check(2, "status")));
+    }
+
+    #[test]
+    fn chats_are_limited_together_and_inline_queries_are_not() {
+        let limiter = RateLimiter::default();
+        let mut config = config();
+        config.chat.burst = 1;
+        let now = Instant::now();
+
+        assert!(allowed(limiter.check_at(
+            &config,
+            Role::User,
+            1,
+            Some(CHAT),
+            "ping",
+            now
+        )));
+        assert!(!allowed(limiter.check_at(
+            &config,
+            Role::User,
+            2,
+            Some(CHAT),
+            "ping",
+            now
+        )));
+        assert!(allowed(limiter.check_at(
+            &config,
+            Role::User,
+            2,
+            None,
+            "ping",
+            now
+        )));
+    }
+
+    #[test]
+    fn admins_are_exempt() {
+        let limiter = RateLimiter::default();
+        let config = config();
+        let now = Instant::now();
+        for _ in 0..10 {
+            assert!(allowed(limiter.check_at(
+                &config,
+                Role::Admin,
+                USER,
+                Some(CHAT),
+                "status",
+                now
+            )));
+        }
+        assert!(allowed(limiter.check_at(
+            &config,
+            Role::Trusted,
+            USER,
+            Some(CHAT),
+            "status",
+            now
+        )));
+        assert!(!allowed(limiter.check_at(
+            &config,
+            Role::Trusted,
+            USER,
+            Some(CHAT),
+            "status",
+            now
+        )));
+    }
+}
@@ -4,22 +4,95 @@
 //! SPDX-License-Identifier: MIT
 //!
 
+use std::{env, path::PathBuf, process};
 use tokio::runtime;
 
+mod app;
 mod cfg;
 mod init;
+mod jobs;
+mod limits;
+mod menu;
+mod outbox;
 mod plugins;
+mod prefixes;
+mod repl;
+mod reporter;
+mod store;
+#[cfg(test)]
+mod testing;
+mod transport;
 
-type Result = std::result::Result<(), Box<dyn std::error::Error>>;
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
-fn main() -> Result {
+const USAGE: &str = "Usage: knight-bot [--config <path>] [--repl]
+
+Options:
+  -c, --config <path>  Config file to use (default: ./config.toml)
+      --repl           Run commands typed in the terminal instead of connecting
+                       to Telegram
+  -h, --help           Print this help
+  -V, --version        Print the version
+
+Config fields can be overridden with KNIGHT_BOT_API_ID, KNIGHT_BOT_API_HASH,
+KNIGHT_BOT_BOT_TOKEN, KNIGHT_BOT_ADMIN_ID, KNIGHT_BOT_ADMINS, KNIGHT_BOT_TRUSTED,
+KNIGHT_BOT_LOG_CHAT_ID and KNIGHT_BOT_SESSION_FILE.";
+
+fn main() {
     pretty_env_logger::init();
 
+    let (config_path, repl) = parse_args();
+
     log::info!("Knight-Bot v{} is initializing...", VERSION);
-    runtime::Builder::new_current_thread()
+    let runtime = runtime::Builder::new_current_thread()
         .enable_all()
         .build()
-        .unwrap()
-        .block_on(init::async_main())
+        .unwrap();
+    let result = if repl {
+        runtime.block_on(repl::run(config_path))
+    } else {
+        runtime.block_on(init::async_main(config_path))
+    };
+
+    if let Err(e) = result {
+        log::error!("{}", e);
+        process::exit(1);
+    }
+}
+
+/// Parses the command line into the config path and whether to run the
+/// REPL, exiting on `--help`, `--version` or bad usage.
+fn parse_args() -> (PathBuf, bool) {
+    let mut config_path = PathBuf::from(cfg::DEFAULT_PATH);
+    let mut repl = false;
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-c" | "--config" => match args.next() {
+                Some(path) => config_path = PathBuf::from(path),
+                None => usage_error(&format!("{} needs a path", arg)),
+            },
+            "--repl" => repl = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            "-V" | "--version" => {
+                println!("knight-bot {}", VERSION);
+                process::exit(0);
+            }
+            _ => match arg.strip_prefix("--config=") {
+                Some(path) => config_path = PathBuf::from(path),
+                None => usage_error(&format!("unknown argument `{}`", arg)),
+            },
+        }
+    }
+
+    (config_path, repl)
+}
+
+fn usage_error(msg: &str) -> ! {
+    eprintln!("error: {}\n\n{}", msg, USAGE);
+    process::exit(2);
 }
@@ -0,0 +1,211 @@
+//!
+//! Copyright (C) 2023-2025 cyberknight777
+//!
+//! SPDX-License-Identifier: MIT
+//!
+
+use crate::app::AppContext;
+use crate::cfg::Role;
+use crate::plugins::{self, Plugin};
+use grammers_client::{Client, InvocationError};
+use grammers_session::{PackedChat, PackedType};
+use grammers_tl_types as tl;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Admin commands can't be listed with their `k.` prefix, Telegram only
+/// takes `[a-z0-9_]` in command names, so they are listed as `/k_<name>`
+/// and accepted in that form too.
+pub const ADMIN_PREFIX: &str = "k_";
+
+/// Key under which users given their own menu are stored, so it can be
+/// taken away again once they are no longer admins.
+const SCOPED_USERS: &str = "scoped_users";
+
+/// What [`publish`] did.
+pub struct Published {
+    pub public: usize,
+    pub chat_admins: usize,
+    /// Users who got a menu with admin commands
+    pub users: usize,
+    /// Users whose menu could not be set, usually because they never
+    /// talked to the bot so it can't address them
+    pub failed: Vec<i64>,
+}
+
+/// Publishes the command menu shown by Telegram clients.
+///
+/// Everyone sees the public commands, group admins additionally see those
+/// for chat admins, and the owner, admins and trusted users see everything
+/// their role allows in their private chat with the bot. `known` are chats
+/// already at hand with their access hash, such as that of whoever asked
+/// for a resync.
+pub async fn publish(
+    app: &AppContext,
+    client: &Client,
+    known: &[PackedChat],
+) -> Result<Published, InvocationError> {
+    let config = app.config();
+    let public = commands(Role::User);
+    let chat_admins = commands(Role::ChatAdmin);
+    set(client, tl::enums::BotCommandScope::Default, &public).await?;
+    set(client, tl::enums::BotCommandScope::ChatAdmins, &chat_admins).await?;
+
+    let mut roles = BTreeMap::new();
+    for user in config.trusted.iter() {
+        roles.insert(*user, Role::Trusted);
+    }
+    for user in config.admins.iter() {
+        roles.insert(*user, Role::Admin);
+    }
+    roles.insert(config.admin_id, Role::Owner);
+
+    let mut failed = Vec::new();
+    for (user, role) in &roles {
+        let peer = user_peer(*user, known);
+        if let Err(e) = set(client, peer_scope(peer), &commands(*role)).await {
+            log::warn!("Failed to set the command menu of {}: {}", user, e);
+            failed.push(*user);
+        }
+    }
+
+    // Users who had a menu of their own last time but have lost their role
+    // since go back to the public one.
+    let store = app.store.plugin("menu");
+    let scoped = roles.keys().copied().collect::<BTreeSet<_>>();
+    let previous = store.get::<BTreeSet<i64>>(SCOPED_USERS).unwrap_or_default();
+    for user in previous.difference(&scoped) {
+        let request = tl::functions::bots::ResetBotCommands {
+            scope: peer_scope(user_peer(*user, known)),
+            lang_code: String::new(),
+        };
+        if let Err(e) = client.invoke(&request).await {
+            log::warn!("Failed to reset the command menu of {}: {}", user, e);
+        }
+    }
+    if let Err(e) = store.set(SCOPED_USERS, &scoped).await {
+        log::warn!("Failed to remember users with a command menu: {}", e);
+    }
+
+    Ok(Published {
+        public: public.len(),
+        chat_admins: chat_admins.len(),
+        users: roles.len() - failed.len(),
+        failed,
+    })
+}
+
+/// Name a command is listed under in the menu.
+fn menu_name(plugin: &dyn Plugin) -> String {
+    if plugins::is_admin_command(plugin) {
+        format!("{}{}", ADMIN_PREFIX, plugin.name())
+    } else {
+        plugin.name().to_owned()
+    }
+}
+
+/// Every command `role` may run, public ones first.
+fn listed(role: Role) -> Vec<&'static dyn Plugin> {
+    let mut commands = plugins::REGISTRY
+        .iter()
+        .copied()
+        .filter(|p| p.role() <= role)
+        .collect::<Vec<_>>();
+    commands.sort_by_key(|p| (plugins::is_admin_command(*p), p.category(), p.name()));
+    commands
+}
+
+/// The menu entries for [`listed`] commands.
+fn commands(role: Role) -> Vec<tl::enums::BotCommand> {
+    listed(role)
+        .into_iter()
+        .map(|p| {
+            tl::types::BotCommand {
+                command: menu_name(p),
+                description: p.description().to_owned(),
+            }
+            .into()
+        })
+        .collect()
+}
+
+async fn set(
+    client: &Client,
+    scope: tl::enums::BotCommandScope,
+    commands: &[tl::enums::BotCommand],
+) -> Result<(), InvocationError> {
+    let request = tl::functions::bots::SetBotCommands {
+        scope,
+        lang_code: String::new(),
+        commands: commands.to_vec(),
+    };
+    client.invoke(&request).await?;
+    Ok(())
+}
+
+/// `user`'s private chat, with its access hash if it is among `known`.
+fn user_peer(user: i64, known: &[PackedChat]) -> PackedChat {
+    known
+        .iter()
+        .find(|chat| chat.ty == PackedType::User && chat.id == user)
+        .copied()
+        .unwrap_or(PackedChat {
+            ty: PackedType::User,
+            id: user,
+            access_hash: None,
+        })
+}
+
+fn peer_scope(peer: PackedChat) -> tl::enums::BotCommandScope {
+    tl::types::BotCommandScopePeer {
+        peer: peer.to_input_peer(),
+    }
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(role: Role) -> Vec<String> {
+        listed(role).into_iter().map(menu_name).collect()
+    }
+
+    #[test]
+    fn everyone_sees_public_commands_only() {
+        let public = names(Role::User);
+        assert!(public.iter().any(|name| name == "help"));
+        assert!(public.iter().any(|name| name == "whois"));
+        assert!(!public.iter().any(|name| name == "disable"));
+        assert!(!public.iter().any(|name| name.starts_with(ADMIN_PREFIX)));
+        // Trusted users have no commands of their own yet.
+        assert_eq!(public, names(Role::Trusted));
+    }
+
+    #[test]
+    fn chat_admins_also_see_chat_settings() {
+        let chat_admin = names(Role::ChatAdmin);
+        for name in ["disable", "disabled", "enable", "help"] {
+            assert!(chat_admin.iter().any(|n| n == name), "{} missing", name);
+        }
+        assert!(!chat_admin.iter().any(|name| name.starts_with(ADMIN_PREFIX)));
+    }
+
+    #[test]
+    fn admin_commands_come_last_with_their_prefix() {
+        let admin = names(Role::Admin);
+        assert!(admin.iter().any(|name| name == "k_reload"));
+        assert!(!admin.iter().any(|name| name == "k_sh"));
+
+        let owner = names(Role::Owner);
+        assert_eq!(owner.len(), plugins::REGISTRY.len());
+        assert!(owner.iter().any(|name| name == "k_sh"));
+        let first_admin = owner
+            .iter()
+            .position(|name| name.starts_with(ADMIN_PREFIX))
+            .unwrap();
+        assert!(owner[first_admin..]
+            .iter()
+            .all(|name| name.starts_with(ADMIN_PREFIX)));
+        assert_eq!(owner[..first_admin], names(Role::ChatAdmin));
+    }
+}
@@ -0,0 +1,231 @@
+//!
+//! Copyright (C) 2023-2025 cyberknight777
+//!
+//! SPDX-License-Identifier: MIT
+//!
+
+use crate::cfg::OutboxConfig;
+use grammers_client::InvocationError;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Counters shown by `/status`.
+#[derive(Default)]
+pub struct OutboxStats {
+    /// Messages waiting for their turn or being sent
+    pub queued: usize,
+    /// Chats with messages queued
+    pub chats: usize,
+    pub sent: u64,
+    pub flood_waits: u64,
+}
+
+/// Errors that may be Telegram asking us to wait before trying again.
+pub trait FloodWait {
+    /// How long to wait, if this is a flood wait.
+    fn flood_wait(&self) -> Option<Duration>;
+}
+
+impl FloodWait for InvocationError {
+    fn flood_wait(&self) -> Option<Duration> {
+        match self {
+            InvocationError::Rpc(e) if e.name.starts_with("FLOOD") || e.name == "SLOWMODE_WAIT" => {
+                Some(Duration::from_secs(e.value.unwrap_or(1) as u64))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Queue {
+    /// Held while a message is being sent; tokio's mutex is fair, so messages
+    /// go out in the order they were queued.
+    turn: Arc<tokio::sync::Mutex<()>>,
+    depth: usize,
+}
+
+/// Sends messages one at a time per chat, sitting out flood waits.
+pub struct Outbox {
+    queues: Mutex<HashMap<i64, Queue>>,
+    max_wait: Duration,
+    retries: u32,
+    sent: AtomicU64,
+    flood_waits: AtomicU64,
+}
+
+impl Outbox {
+    pub fn new(config: &OutboxConfig) -> Self {
+        Outbox {
+            queues: Mutex::new(HashMap::new()),
+            max_wait: Duration::from_secs(config.max_wait_secs),
+            retries: config.retries,
+            sent: AtomicU64::new(0),
+            flood_waits: AtomicU64::new(0),
+        }
+    }
+
+    pub fn stats(&self) -> OutboxStats {
+        let queues = self.queues.lock().unwrap();
+        OutboxStats {
+            queued: queues.values().map(|queue| queue.depth).sum(),
+            chats: queues.len(),
+            sent: self.sent.load(Ordering::Relaxed),
+            flood_waits: self.flood_waits.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Runs `send` once it is `chat`'s turn, retrying after flood waits.
+    pub async fn queued<T, E, F, Fut>(&self, chat: i64, mut send: F) -> Result<T, E>
+    where
+        E: FloodWait,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let turn = {
+            let mut queues = self.queues.lock().unwrap();
+            let queue = queues.entry(chat).or_default();
+            queue.depth += 1;
+            queue.turn.clone()
+        };
+        let _ticket = Ticket { outbox: self, chat };
+        let _turn = turn.lock().await;
+
+        let mut attempt = 0;
+        loop {
+            let result = send().await;
+            let wait = result.as_ref().err().and_then(FloodWait::flood_wait);
+            match wait {
+                Some(wait) if attempt < self.retries && wait <= self.max_wait => {
+                    attempt += 1;
+                    self.flood_waits.fetch_add(1, Ordering::Relaxed);
+                    log::warn!("Flood wait of {:?} in chat {}, retrying", wait, chat);
+                    tokio::time::sleep(wait).await;
+                }
+                _ => {
+                    if result.is_ok() {
+                        self.sent.fetch_add(1, Ordering::Relaxed);
+                    }
+                    return result;
+                }
+            }
+        }
+    }
+}
+
+/// Takes a message off its chat's queue when dropped, even if the send was cancelled.
+struct Ticket<'a> {
+    outbox: &'a Outbox,
+    chat: i64,
+}
+
+impl Drop for Ticket<'_> {
+    fn drop(&mut self) {
+        let mut queues = self.outbox.queues.lock().unwrap();
+        if let Some(queue) = queues.get_mut(&self.chat) {
+            queue.depth -= 1;
+            if queue.depth == 0 {
+                queues.remove(&self.chat);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicU32;
+
+    /// A flood wait of the given length.
+    #[derive(Debug, PartialEq)]
+    struct Flood(Duration);
+
+    impl FloodWait for Flood {
+        fn flood_wait(&self) -> Option<Duration> {
+            Some(self.0)
+        }
+    }
+
+    fn outbox(max_wait_secs: u64, retries: u32) -> Outbox {
+        Outbox::new(&OutboxConfig {
+            max_wait_secs,
+            retries,
+        })
+    }
+
+    #[tokio::test]
+    async fn flood_waits_are_sat_out() {
+        let outbox = outbox(1, 3);
+        let attempts = AtomicU32::new(0);
+        let attempts = &attempts;
+        let result = outbox
+            .queued(1, move || async move {
+                match attempts.fetch_add(1, Ordering::Relaxed) {
+                    0 => Err(Flood(Duration::from_millis(10))),
+                    _ => Ok("sent"),
+                }
+            })
+            .await;
+        assert_eq!(result, Ok("sent"));
+        assert_eq!(attempts.load(Ordering::Relaxed), 2);
+        let stats = outbox.stats();
+        assert_eq!((stats.sent, stats.flood_waits), (1, 1));
+        assert_eq!((stats.queued, stats.chats), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn long_or_repeated_flood_waits_fail() {
+        let outbox = outbox(1, 1);
+        let attempts = AtomicU32::new(0);
+        let attempts = &attempts;
+
+        let result: Result<(), _> = outbox
+            .queued(1, move || async move {
+                attempts.fetch_add(1, Ordering::Relaxed);
+                Err(Flood(Duration::from_secs(5)))
+            })
+            .await;
+        assert_eq!(result, Err(Flood(Duration::from_secs(5))));
+        assert_eq!(attempts.swap(0, Ordering::Relaxed), 1);
+
+        let result: Result<(), _> = outbox
+            .queued(1, move || async move {
+                attempts.fetch_add(1, Ordering::Relaxed);
+                Err(Flood(Duration::from_millis(1)))
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::Relaxed), 2);
+        assert_eq!(outbox.stats().sent, 0);
+    }
+
+    #[tokio::test]
+    async fn chats_are_sent_to_in_order_and_independently() {
+        let outbox = outbox(1, 3);
+        let log = Mutex::new(Vec::new());
+        let log = &log;
+        let send = |name: &'static str, chat: i64, flood: bool| {
+            let attempts = AtomicU32::new(0);
+            outbox.queued(chat, move || {
+                let first = attempts.fetch_add(1, Ordering::Relaxed) == 0;
+                async move {
+                    log.lock().unwrap().push(name);
+                    if flood && first {
+                        Err(Flood(Duration::from_millis(20)))
+                    } else {
+                        Ok(())
+                    }
+                }
+            })
+        };
+
+        let (a, b, c) = tokio::join!(send("a", 1, true), send("b", 1, false), send("c", 2, false));
+        assert!(a.is_ok() && b.is_ok() && c.is_ok());
+        // `b` waits for `a` to get through its flood wait, `c` in another
+        // chat does not.
+        assert_eq!(*log.lock().unwrap(), ["a", "c", "a", "b"]);
+    }
+}
@@ -4,37 +4,78 @@
 //! SPDX-License-Identifier: MIT
 //!
 
-// Description: Sends a why do you ask text.
+use crate::app::AppContext;
+use crate::plugins::{error::BotError, BoxFuture, Category, Context, Plugin};
+use crate::transport::{Button, Incoming, Outgoing};
+use std::sync::Arc;
 
-use grammers_client::{
-    button, reply_markup,
-    types::{InputMessage, Message},
-    Client,
-};
+type Result = std::result::Result<(), BotError>;
 
-type Result = std::result::Result<(), Box<dyn std::error::Error>>;
+pub struct Anyone;
 
-pub async fn knightcmd_anyone(client: Client, message: Message) -> Result {
+impl Plugin for Anyone {
+    fn name(&self) -> &'static str {
+        "anyone"
+    }
+
+    fn description(&self) -> &'static str {
+        "Sends a why do you ask text."
+    }
+
+    fn category(&self) -> Category {
+        Category::Fun
+    }
+
+    fn run(&self, ctx: Context) -> BoxFuture {
+        Box::pin(knightcmd_anyone(ctx.app, ctx.message))
+    }
+}
+
+pub async fn knightcmd_anyone(app: Arc<AppContext>, message: Incoming) -> Result {
     if let Some(id) = message.reply_to_message_id() {
-        client
-            .send_message(
+        app.transport
+            .send(
                 message.chat(),
-                InputMessage::html("Hmm.")
+                Outgoing::html("Hmm.")
                     .reply_to(Some(id))
-                    .reply_markup(&reply_markup::inline(vec![vec![button::url(
+                    .buttons(vec![vec![Button::url(
                         "Why do you ask?",
                         "https://dontasktoask.com",
-                    )]])),
+                    )]]),
             )
             .await?;
     } else {
-        message
+        app.transport
             .reply(
-                InputMessage::html("Hmm.").reply_markup(&reply_markup::inline(vec![vec![
-                    button::url("Why do you ask?", "https://dontasktoask.com"),
-                ]])),
+                &message,
+                Outgoing::html("Hmm.").buttons(vec![vec![Button::url(
+                    "Why do you ask?",
+                    "https://dontasktoask.com",
+                )]]),
             )
             .await?;
     }
     return Ok(());
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, TestBot};
+
+    #[tokio::test]
+    async fn links_dontasktoask() {
+        let bot = TestBot::new();
+        let message = testing::message(2, "/anyone").in_reply_to(Some(1));
+        knightcmd_anyone(bot.app(), message).await.unwrap();
+        assert_eq!(
+            bot.take_sent(),
+            Outgoing::html("Hmm.")
+                .reply_to(Some(1))
+                .buttons(vec![vec![Button::url(
+                    "Why do you ask?",
+                    "https://dontasktoask.com"
+                )]])
+        );
+    }
+}
@@ -0,0 +1,417 @@
+//!
+//! Copyright (C) 2023-2025 cyberknight777
+//!
+//! SPDX-License-Identifier: MIT
+//!
+
+use crate::plugins::Plugin;
+use reqwest::Url;
+use std::collections::HashMap;
+use std::fmt;
+
+/// What a parameter accepts.
+#[derive(Clone, Copy)]
+pub enum Kind {
+    /// A single word, or a quoted string.
+    Word,
+    /// A whole number within the given bounds (inclusive).
+    Int(i64, i64),
+    /// An `http` or `https` URL.
+    Url,
+    /// Everything that is left, verbatim, including quotes and newlines.
+    Rest,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Style {
+    Positional,
+    Flag,
+    Option,
+}
+
+/// A parameter a command takes, see [`Plugin::params`].
+pub struct Param {
+    name: &'static str,
+    kind: Kind,
+    style: Style,
+    short: Option<char>,
+    required: bool,
+}
+
+impl Param {
+    pub const fn required(name: &'static str, kind: Kind) -> Self {
+        Param {
+            name,
+            kind,
+            style: Style::Positional,
+            short: None,
+            required: true,
+        }
+    }
+
+    pub const fn optional(name: &'static str, kind: Kind) -> Self {
+        Param {
+            required: false,
+            ..Param::required(name, kind)
+        }
+    }
+
+    /// A `--name` / `-s` switch without a value.
+    pub const fn flag(name: &'static str, short: char) -> Self {
+        Param {
+            name,
+            kind: Kind::Word,
+            style: Style::Flag,
+            short: Some(short),
+            required: false,
+        }
+    }
+
+    /// A `--name value` / `-s value` option.
+    pub const fn option(name: &'static str, short: char, kind: Kind) -> Self {
+        Param {
+            name,
+            kind,
+            style: Style::Option,
+            short: Some(short),
+            required: false,
+        }
+    }
+}
+
+/// Why the arguments did not match a command's parameters.
+#[derive(Debug, PartialEq)]
+pub enum ArgError {
+    UnclosedQuote,
+    UnknownOption(String),
+    MissingValue(&'static str),
+    Missing(&'static str),
+    Invalid {
+        name: &'static str,
+        value: String,
+        expected: String,
+    },
+    TooMany(String),
+}
+
+impl fmt::Display for ArgError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ArgError::UnclosedQuote => f.write_str("A quote is never closed."),
+            ArgError::UnknownOption(option) => write!(f, "Unknown option {}.", option),
+            ArgError::MissingValue(name) => write!(f, "--{} needs a value.", name),
+            ArgError::Missing(name) => write!(f, "Missing <{}>.", name),
+            ArgError::Invalid {
+                name,
+                value,
+                expected,
+            } => write!(f, "<{}> must be {}, not `{}`.", name, expected, value),
+            ArgError::TooMany(extra) => write!(f, "Unexpected argument `{}`.", extra),
+        }
+    }
+}
+
+/// Arguments parsed according to a command's [`Param`]s.
+#[derive(Default, Debug)]
+pub struct Args {
+    values: HashMap<&'static str, String>,
+    flags: Vec<&'static str>,
+}
+
+impl Args {
+    /// Value of a positional parameter or option.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    /// Value of an [`Kind::Int`] parameter.
+    pub fn int(&self, name: &str) -> Option<i64> {
+        self.get(name).and_then(|value| value.parse().ok())
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.flags.contains(&name)
+    }
+}
+
+/// Parses `raw` (the text after the command name) according to `params`.
+///
+/// Words are split on whitespace, quotes group words together and `\` escapes
+/// the next character inside double quotes. Options are only recognised if
+/// the command has any, and not after `--`.
+pub fn parse(params: &'static [Param], raw: &str) -> Result<Args, ArgError> {
+    let positionals = params
+        .iter()
+        .filter(|p| p.style == Style::Positional)
+        .collect::<Vec<_>>();
+    let mut options = params.iter().any(|p| p.style != Style::Positional);
+
+    let mut args = Args::default();
+    let mut lexer = Lexer { raw, pos: 0 };
+    let mut next = 0;
+    loop {
+        lexer.skip_whitespace();
+        if lexer.at_end() {
+            break;
+        }
+        if let Some(param) = positionals.get(next) {
+            if matches!(param.kind, Kind::Rest) && !(options && lexer.peek() == Some('-')) {
+                args.values
+                    .insert(param.name, lexer.rest().trim_end().to_owned());
+                next += 1;
+                break;
+            }
+        }
+
+        let (token, quoted) = lexer.token()?;
+        if options && !quoted && token.starts_with('-') && token.len() > 1 {
+            let expects_int = positionals
+                .get(next)
+                .map_or(false, |p| matches!(p.kind, Kind::Int(..)));
+            if token == "--" {
+                options = false;
+                continue;
+            }
+            if !(expects_int && token.parse::<i64>().is_ok()) {
+                let (param, inline) = find_option(params, &token)?;
+                match param.style {
+                    Style::Flag if inline.is_none() => args.flags.push(param.name),
+                    Style::Flag => return Err(ArgError::UnknownOption(token)),
+                    _ => {
+                        let value = match inline {
+                            Some(value) => value,
+                            None => {
+                                lexer.skip_whitespace();
+                                if lexer.at_end() {
+                                    return Err(ArgError::MissingValue(param.name));
+                                }
+                                lexer.token()?.0
+                            }
+                        };
+                        args.values.insert(param.name, check(param, value)?);
+                    }
+                }
+                continue;
+            }
+        }
+
+        match positionals.get(next) {
+            Some(param) => {
+                args.values.insert(param.name, check(param, token)?);
+                next += 1;
+            }
+            None => return Err(ArgError::TooMany(token)),
+        }
+    }
+
+    if let Some(param) = positionals[next.min(positionals.len())..]
+        .iter()
+        .find(|p| p.required)
+    {
+        return Err(ArgError::Missing(param.name));
+    }
+    Ok(args)
+}
+
+/// Usage line for a command, e.g. `<code> [--full]`, derived from its params.
+pub fn usage(plugin: &dyn Plugin) -> String {
+    plugin
+        .params()
+        .iter()
+        .map(|p| {
+            let name = match (p.style, p.kind) {
+                (Style::Flag, _) => format!("--{}", p.name),
+                (Style::Option, _) => format!("--{} {}", p.name, p.name),
+                (_, Kind::Rest) => format!("{}...", p.name),
+                _ => p.name.to_owned(),
+            };
+            if p.required {
+                format!("<{}>", name)
+            } else {
+                format!("[{}]", name)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn find_option(
+    params: &'static [Param],
+    token: &str,
+) -> Result<(&'static Param, Option<String>), ArgError> {
+    let options = params.iter().filter(|p| p.style != Style::Positional);
+    let found = if let Some(long) = token.strip_prefix("--") {
+        let (name, inline) = match long.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_owned())),
+            None => (long, None),
+        };
+        options
+            .filter(|p| p.name == name)
+            .map(|p| (p, inline.clone()))
+            .next()
+    } else {
+        let mut chars = token[1..].chars();
+        match (chars.next(), chars.as_str()) {
+            (Some(short), rest) => options
+                .filter(|p| p.short == Some(short))
+                .map(|p| (p, (!rest.is_empty()).then(|| rest.to_owned())))
+                .next(),
+            (None, _) => None,
+        }
+    };
+    found.ok_or_else(|| ArgError::UnknownOption(token.to_owned()))
+}
+
+fn check(param: &Param, value: String) -> Result<String, ArgError> {
+    let invalid = |expected: String| ArgError::Invalid {
+        name: param.name,
+        value: value.clone(),
+        expected,
+    };
+    match param.kind {
+        Kind::Word | Kind::Rest => {}
+        Kind::Int(min, max) => match value.parse::<i64>() {
+            Ok(n) if (min..=max).contains(&n) => {}
+            _ => return Err(invalid(format!("a number from {} to {}", min, max))),
+        },
+        Kind::Url => match Url::parse(&value) {
+            Ok(url) if url.scheme() == "http" || url.scheme() == "https" => {}
+            _ => return Err(invalid("an http(s) URL".to_owned())),
+        },
+    }
+    Ok(value)
+}
+
+struct Lexer<'a> {
+    raw: &'a str,
+    pos: usize,
+}
+
+impl Lexer<'_> {
+    fn at_end(&self) -> bool {
+        self.pos >= self.raw.len()
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.raw[self.pos..].chars().next()
+    }
+
+    fn rest(&self) -> &str {
+        &self.raw[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Reads one word, returning whether any part of it was quoted.
+    fn token(&mut self) -> Result<(String, bool), ArgError> {
+        let mut token = String::new();
+        let mut quoted = false;
+        let mut chars = self.raw[self.pos..].char_indices();
+        let mut end = self.raw.len() - self.pos;
+        while let Some((i, c)) = chars.next() {
+            match c {
+                c if c.is_whitespace() => {
+                    end = i;
+                    break;
+                }
+                '"' | '\'' => {
+                    quoted = true;
+                    let quote = c;
+                    loop {
+                        match chars.next() {
+                            Some((_, c)) if c == quote => break,
+                            Some((_, '\\')) if quote == '"' => match chars.next() {
+                                Some((_, c)) => token.push(c),
+                                None => return Err(ArgError::UnclosedQuote),
+                            },
+                            Some((_, c)) => token.push(c),
+                            None => return Err(ArgError::UnclosedQuote),
+                        }
+                    }
+                }
+                c => token.push(c),
+            }
+        }
+        self.pos += end;
+        Ok((token, quoted))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static PARAMS: &[Param] = &[
+        Param::required("code", Kind::Int(100, 599)),
+        Param::optional("name", Kind::Word),
+        Param::flag("full", 'f'),
+        Param::option("lang", 'l', Kind::Word),
+    ];
+
+    static REST: &[Param] = &[Param::optional("command", Kind::Rest)];
+
+    #[test]
+    fn parses_positionals_flags_and_options() {
+        let args = parse(PARAMS, "404 \"two words\" -f --lang=en").unwrap();
+        assert_eq!(args.int("code"), Some(404));
+        assert_eq!(args.get("name"), Some("two words"));
+        assert!(args.flag("full"));
+        assert_eq!(args.get("lang"), Some("en"));
+
+        let args = parse(PARAMS, "-l de 200").unwrap();
+        assert_eq!(args.get("lang"), Some("de"));
+        assert!(!args.flag("full"));
+    }
+
+    #[test]
+    fn reports_bad_input() {
+        assert!(matches!(
+            parse(PARAMS, "abc"),
+            Err(ArgError::Invalid { name: "code", .. })
+        ));
+        assert_eq!(parse(PARAMS, ""), Err(ArgError::Missing("code")));
+        assert_eq!(
+            parse(PARAMS, "200 a b"),
+            Err(ArgError::TooMany("b".to_owned()))
+        );
+        assert_eq!(
+            parse(PARAMS, "200 --nope"),
+            Err(ArgError::UnknownOption("--nope".to_owned()))
+        );
+        assert_eq!(
+            parse(PARAMS, "200 --lang"),
+            Err(ArgError::MissingValue("lang"))
+        );
+        assert_eq!(parse(PARAMS, "200 \"open"), Err(ArgError::UnclosedQuote));
+    }
+
+    #[test]
+    fn rest_is_kept_verbatim() {
+        let raw = "echo \"a  b\" | tr -d 'x'\nls -la";
+        assert_eq!(parse(REST, raw).unwrap().get("command"), Some(raw));
+        assert_eq!(parse(REST, "").unwrap().get("command"), None);
+    }
+
+    #[test]
+    fn usage_is_generated() {
+        struct Test;
+        impl Plugin for Test {
+            fn name(&self) -> &'static str {
+                "test"
+            }
+            fn description(&self) -> &'static str {
+                ""
+            }
+            fn params(&self) -> &'static [Param] {
+                PARAMS
+            }
+            fn run(&self, _: crate::plugins::Context) -> crate::plugins::BoxFuture {
+                Box::pin(async { Ok(()) })
+            }
+        }
+        assert_eq!(usage(&Test), "<code> [name] [--full] [--lang lang]");
+    }
+}
@@ -4,153 +4,272 @@
 //! SPDX-License-Identifier: MIT
 //!
 
-// Description: Gets package information from AUR.
+use crate::app::AppContext;
+use crate::plugins::{
+    args::{Args, Kind, Param},
+    error::BotError,
+    html::Html,
+    inline::{Inline, InlineFuture, InlineResult},
+    output::Output,
+    req::{self, ReqError},
+    BoxFuture, Category, Context, Plugin,
+};
+use crate::transport::{Incoming, Outgoing};
+use serde::Deserialize;
+use std::sync::Arc;
 
-use crate::plugins;
-use grammers_client::types::{InputMessage, Message};
+type Result = std::result::Result<(), BotError>;
 
-type Result = std::result::Result<(), Box<dyn std::error::Error>>;
+pub struct Aur;
 
-pub async fn knightcmd_aur(message: Message, pkg: String) -> Result {
-    if !pkg.is_empty() {
-        let response = plugins::req::make_request(format!(
-            "https://aur.archlinux.org/rpc/?v=5&type=info&arg={}",
-            pkg
+impl Plugin for Aur {
+    fn name(&self) -> &'static str {
+        "aur"
+    }
+
+    fn description(&self) -> &'static str {
+        "Gets package information from AUR."
+    }
+
+    fn category(&self) -> Category {
+        Category::DevTools
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["paru"]
+    }
+
+    fn params(&self) -> &'static [Param] {
+        const PARAMS: &[Param] = &[Param::optional("package", Kind::Word)];
+        PARAMS
+    }
+
+    fn inline(&self) -> Option<&dyn Inline> {
+        Some(self)
+    }
+
+    fn run(&self, ctx: Context) -> BoxFuture {
+        Box::pin(knightcmd_aur(
+            ctx.app,
+            ctx.message,
+            ctx.parsed.get("package").unwrap_or_default().to_owned(),
         ))
-        .await;
-        if let Some(result) = response.and_then(|val| val.get("results").cloned()) {
-            if let Some(pkg_info) = result.get(0) {
-                let name = pkg_info
-                    .get("Name")
-                    .and_then(|val| val.as_str())
-                    .unwrap_or_default();
-                let ver = pkg_info
-                    .get("Version")
-                    .and_then(|val| val.as_str())
-                    .unwrap_or_default();
-                let desc = pkg_info
-                    .get("Description")
-                    .and_then(|val| val.as_str())
-                    .unwrap_or_default();
-                let url = pkg_info
-                    .get("URL")
-                    .and_then(|val| val.as_str())
-                    .unwrap_or_default();
-                let grp = pkg_info
-                    .get("Groups")
-                    .and_then(|val| val.as_array())
-                    .map(|arr| {
-                        arr.iter()
-                            .map(|val| val.as_str().unwrap_or_default())
-                            .collect::<Vec<_>>()
-                    })
-                    .unwrap_or_default();
-                let lic = pkg_info
-                    .get("License")
-                    .and_then(|val| val.as_array())
-                    .map(|arr| {
-                        arr.iter()
-                            .map(|val| val.as_str().unwrap_or_default())
-                            .collect::<Vec<_>>()
-                    })
-                    .unwrap_or_default();
-                let prov = pkg_info
-                    .get("Provides")
-                    .and_then(|val| val.as_array())
-                    .map(|arr| {
-                        arr.iter()
-                            .map(|val| val.as_str().unwrap_or_default())
-                            .collect::<Vec<_>>()
-                    })
-                    .unwrap_or_default();
-                let dep = pkg_info
-                    .get("Depends")
-                    .and_then(|val| val.as_array())
-                    .map(|arr| {
-                        arr.iter()
-                            .map(|val| val.as_str().unwrap_or_default())
-                            .collect::<Vec<_>>()
-                    })
-                    .unwrap_or_default();
-                let mkdep = pkg_info
-                    .get("MakeDepends")
-                    .and_then(|val| val.as_array())
-                    .map(|arr| {
-                        arr.iter()
-                            .map(|val| val.as_str().unwrap_or_default())
-                            .collect::<Vec<_>>()
-                    })
-                    .unwrap_or_default();
-                let chkdep = pkg_info
-                    .get("CheckDepends")
-                    .and_then(|val| val.as_array())
-                    .map(|arr| {
-                        arr.iter()
-                            .map(|val| val.as_str().unwrap_or_default())
-                            .collect::<Vec<_>>()
-                    })
-                    .unwrap_or_default();
-                let optdep = pkg_info
-                    .get("OptDepends")
-                    .and_then(|val| val.as_array())
-                    .map(|arr| {
-                        arr.iter()
-                            .map(|val| val.as_str().unwrap_or_default())
-                            .collect::<Vec<_>>()
-                    })
-                    .unwrap_or_default();
-                let conf = pkg_info
-                    .get("Conflicts")
-                    .and_then(|val| val.as_array())
-                    .map(|arr| {
-                        arr.iter()
-                            .map(|val| val.as_str().unwrap_or_default())
-                            .collect::<Vec<_>>()
-                    })
-                    .unwrap_or_default();
-                let maint = pkg_info
-                    .get("Maintainer")
-                    .and_then(|val| val.as_str())
-                    .unwrap_or_default();
-                message
-                    .reply(InputMessage::html(format!(
-                        "<b>Name</b>: <code>{}</code>
-<b>Version</b>: <code>{}</code>
-<b>Description</b>: {}
-<b>URL</b>: {}
-<b>Groups</b>: {:?}
-<b>Licenses</b>: {:?}
-<b>Provides</b>: {:?}
-<b>Depends On</b>: {:?}
-<b>Make Deps</b>: {:?}
-<b>Check Deps</b>: {:?}
-<b>Optional Deps</b>: {:?}
-<b>Conflicts With</b>: {:?}
-<b>Maintainer</b>: {}
-",
-                        name,
-                        ver,
-                        desc,
-                        url,
-                        grp,
-                        lic,
-                        prov,
-                        dep,
-                        mkdep,
-                        chkdep,
-                        optdep,
-                        conf,
-                        maint
-                    )))
+    }
+}
+
+/// Response of the AUR RPC `info` endpoint.
+#[derive(Deserialize)]
+struct AurResponse {
+    results: Vec<AurPackage>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AurPackage {
+    name: String,
+    version: String,
+    description: Option<String>,
+    #[serde(rename = "URL")]
+    url: Option<String>,
+    #[serde(default)]
+    groups: Vec<String>,
+    #[serde(default)]
+    license: Vec<String>,
+    #[serde(default)]
+    provides: Vec<String>,
+    #[serde(default)]
+    depends: Vec<String>,
+    #[serde(default)]
+    make_depends: Vec<String>,
+    #[serde(default)]
+    check_depends: Vec<String>,
+    #[serde(default)]
+    opt_depends: Vec<String>,
+    #[serde(default)]
+    conflicts: Vec<String>,
+    maintainer: Option<String>,
+}
+
+impl Inline for Aur {
+    fn answer(&self, app: Arc<AppContext>, args: Args) -> InlineFuture {
+        let pkg = args.get("package").unwrap_or_default().to_owned();
+        Box::pin(async move {
+            if pkg.is_empty() {
+                return Err(BotError::bad_input(
+                    "Give me a package to provide info about!",
+                ));
+            }
+            let results = lookup(&app, &pkg).await?.map(|pkg_info| {
+                let title = format!("{} {}", pkg_info.name, pkg_info.version);
+                let description = pkg_info.description.clone().unwrap_or_default();
+                InlineResult::new(title, Outgoing::html(details(&pkg_info).build()))
+                    .description(description)
+            });
+            Ok(results.into_iter().collect())
+        })
+    }
+}
+
+/// Looks `pkg` up, `None` if there is no such package.
+async fn lookup(app: &AppContext, pkg: &str) -> std::result::Result<Option<AurPackage>, ReqError> {
+    let url = req::with_query(
+        "https://aur.archlinux.org/rpc/",
+        &[("v", "5"), ("type", "info"), ("arg", pkg)],
+    );
+    let response = app.http.get_json::<AurResponse>(&url).await?;
+    Ok(response.results.into_iter().next())
+}
+
+fn details(pkg_info: &AurPackage) -> Html {
+    Html::new()
+        .bold("Name")
+        .text(": ")
+        .code(&pkg_info.name)
+        .line()
+        .bold("Version")
+        .text(": ")
+        .code(&pkg_info.version)
+        .line()
+        .field(
+            "Description",
+            pkg_info.description.as_deref().unwrap_or_default(),
+        )
+        .field("URL", pkg_info.url.as_deref().unwrap_or_default())
+        .field("Groups", format!("{:?}", pkg_info.groups))
+        .field("Licenses", format!("{:?}", pkg_info.license))
+        .field("Provides", format!("{:?}", pkg_info.provides))
+        .field("Depends On", format!("{:?}", pkg_info.depends))
+        .field("Make Deps", format!("{:?}", pkg_info.make_depends))
+        .field("Check Deps", format!("{:?}", pkg_info.check_depends))
+        .field("Optional Deps", format!("{:?}", pkg_info.opt_depends))
+        .field("Conflicts With", format!("{:?}", pkg_info.conflicts))
+        .field(
+            "Maintainer",
+            pkg_info.maintainer.as_deref().unwrap_or_default(),
+        )
+}
+
+pub async fn knightcmd_aur(app: Arc<AppContext>, message: Incoming, pkg: String) -> Result {
+    if !pkg.is_empty() {
+        match lookup(&app, &pkg).await {
+            Ok(Some(pkg_info)) => {
+                Output::html(details(&pkg_info))
+                    .reply(&app, &message)
+                    .await?;
+            }
+            Ok(None) => {
+                app.transport
+                    .reply(&message, "No package found!".into())
+                    .await?;
+            }
+            Err(e) => {
+                log::warn!("AUR lookup for {} failed: {:?}", pkg, e);
+                app.transport
+                    .reply(
+                        &message,
+                        format!("Couldn't get package info from AUR, {}!", e).into(),
+                    )
                     .await?;
-            } else {
-                message.reply("No package found!").await?;
             }
         }
     } else {
-        message
-            .reply("Give me a package to provide info about!")
+        app.transport
+            .reply(&message, "Give me a package to provide info about!".into())
             .await?;
     }
     return Ok(());
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, StubServer, TestBot};
+
+    const YAY: &str = r#"{"results": [{
+        "Name": "yay",
+        "Version": "12.4.2-1",
+        "Description": "Yet another yogurt",
+        "URL": "https://github.com/Jguer/yay",
+        "License": ["GPL-3.0-or-later"],
+        "Depends": ["pacman>6.1", "git"],
+        "Maintainer": "jguer"
+    }]}"#;
+
+    fn bot(stub: &StubServer) -> TestBot {
+        TestBot::new().redirect("https://aur.archlinux.org", stub)
+    }
+
+    #[tokio::test]
+    async fn shows_package_info() {
+        let stub = StubServer::start(&[("/rpc/?v=5&type=info&arg=yay", 200, YAY)]).await;
+        let bot = bot(&stub);
+        knightcmd_aur(bot.app(), testing::message(1, "/aur yay"), "yay".to_owned())
+            .await
+            .unwrap();
+        assert_eq!(stub.requests(), ["/rpc/?v=5&type=info&arg=yay"]);
+        let sent = bot.take_sent();
+        assert_eq!(
+            sent.text.lines().take(4).collect::<Vec<_>>(),
+            [
+                "<b>Name</b>: <code>yay</code>",
+                "<b>Version</b>: <code>12.4.2-1</code>",
+                "<b>Description</b>: Yet another yogurt",
+                "<b>URL</b>: https://github.com/Jguer/yay",
+            ]
+        );
+        assert!(sent
+            .text
+            .contains("<b>Depends On</b>: [&quot;pacman&gt;6.1&quot;, &quot;git&quot;]"));
+        assert_eq!(sent.reply_to, Some(1));
+    }
+
+    #[tokio::test]
+    async fn answers_inline_with_the_same_info() {
+        let stub = StubServer::start(&[("/rpc/?v=5&type=info&arg=yay", 200, YAY)]).await;
+        let bot = bot(&stub);
+        let args = crate::plugins::args::parse(Aur.params(), "yay").unwrap();
+        let results = Aur.answer(bot.app(), args).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].title, "yay 12.4.2-1");
+        assert_eq!(results[0].description, "Yet another yogurt");
+        assert!(results[0]
+            .message
+            .text
+            .starts_with("<b>Name</b>: <code>yay</code>"));
+        assert!(bot.take().is_empty());
+    }
+
+    #[tokio::test]
+    async fn says_when_there_is_no_such_package() {
+        let stub =
+            StubServer::start(&[("/rpc/?v=5&type=info&arg=nope", 200, r#"{"results": []}"#)]).await;
+        let bot = bot(&stub);
+        knightcmd_aur(
+            bot.app(),
+            testing::message(1, "/aur nope"),
+            "nope".to_owned(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            bot.take_sent(),
+            Outgoing::text("No package found!").reply_to(Some(1))
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_upstream_errors() {
+        let stub = StubServer::start(&[]).await;
+        let bot = bot(&stub);
+        knightcmd_aur(bot.app(), testing::message(1, "/aur yay"), "yay".to_owned())
+            .await
+            .unwrap();
+        assert_eq!(
+            bot.take_sent(),
+            Outgoing::text(
+                "Couldn't get package info from AUR, the server answered 404 Not Found!"
+            )
+            .reply_to(Some(1))
+        );
+    }
+}
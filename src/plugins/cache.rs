@@ -0,0 +1,94 @@
+//!
+//! Copyright (C) 2023-2025 cyberknight777
+//!
+//! SPDX-License-Identifier: MIT
+//!
+
+use crate::app::AppContext;
+use crate::cfg::Role;
+use crate::plugins::{
+    args::{Args, Kind, Param},
+    error::BotError,
+    html::Html,
+    output::Output,
+    BoxFuture, Context, Plugin,
+};
+use crate::transport::{Incoming, Outgoing};
+use std::sync::Arc;
+
+type Result = std::result::Result<(), BotError>;
+
+pub struct Cache;
+
+impl Plugin for Cache {
+    fn name(&self) -> &'static str {
+        "cache"
+    }
+
+    fn description(&self) -> &'static str {
+        "Shows the API response cache, or flushes it."
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["flush https://ipinfo.io/"]
+    }
+
+    fn params(&self) -> &'static [Param] {
+        const PARAMS: &[Param] = &[
+            Param::optional("action", Kind::Word),
+            Param::optional("prefix", Kind::Word),
+        ];
+        PARAMS
+    }
+
+    fn role(&self) -> Role {
+        Role::Admin
+    }
+
+    fn run(&self, ctx: Context) -> BoxFuture {
+        Box::pin(knightcmd_cache(ctx.app, ctx.message, ctx.parsed))
+    }
+}
+
+pub async fn knightcmd_cache(app: Arc<AppContext>, message: Incoming, args: Args) -> Result {
+    let cache = app.http.cache();
+    match args.get("action") {
+        None => {
+            let stats = cache.stats();
+            let mut text = Html::new()
+                .field("Entries", format!("{}/{}", stats.entries, stats.capacity))
+                .field("Hits", stats.hits.to_string())
+                .field("Misses", stats.misses.to_string())
+                .field("Revalidated", stats.revalidated.to_string());
+            for url in cache.urls() {
+                let state = match url.fresh_for {
+                    Some(ttl) => format!("fresh for {}s", ttl.as_secs()),
+                    None => "stale".to_owned(),
+                };
+                text = text.line().code(&url.url).line().text(format!(
+                    "{}s old, {}",
+                    url.age.as_secs(),
+                    state
+                ));
+            }
+            Output::html(text).reply(&app, &message).await?;
+        }
+        Some("flush") => {
+            let flushed = cache.flush(args.get("prefix"));
+            cache.persist().await;
+            app.transport
+                .reply(
+                    &message,
+                    Outgoing::html(format!("<b>Flushed {} cached response(s)!</b>", flushed)),
+                )
+                .await?;
+        }
+        Some(action) => {
+            return Err(BotError::bad_input(format!(
+                "Unknown action `{}`, try k.cache flush.",
+                action
+            )));
+        }
+    }
+    return Ok(());
+}
@@ -0,0 +1,52 @@
+//!
+//! Copyright (C) 2023-2025 cyberknight777
+//!
+//! SPDX-License-Identifier: MIT
+//!
+
+use crate::app::AppContext;
+use crate::cfg::Role;
+use crate::plugins::{error::BotError, BoxFuture, Context, Plugin};
+use crate::transport::{Incoming, Outgoing};
+use std::sync::Arc;
+
+type Result = std::result::Result<(), BotError>;
+
+pub struct Cancel;
+
+impl Plugin for Cancel {
+    fn name(&self) -> &'static str {
+        "cancel"
+    }
+
+    fn description(&self) -> &'static str {
+        "Kills the replied k.sh job, or every job running in this chat."
+    }
+
+    fn role(&self) -> Role {
+        Role::Owner
+    }
+
+    fn run(&self, ctx: Context) -> BoxFuture {
+        Box::pin(knightcmd_cancel(ctx.app, ctx.message))
+    }
+}
+
+pub async fn knightcmd_cancel(app: Arc<AppContext>, message: Incoming) -> Result {
+    let cancelled = app
+        .jobs
+        .cancel(message.chat().id(), message.reply_to_message_id());
+    if cancelled == 0 {
+        app.transport
+            .reply(&message, Outgoing::html("<b>Nothing to cancel!</b>"))
+            .await?;
+    } else {
+        app.transport
+            .reply(
+                &message,
+                Outgoing::html(format!("<b>Cancelled {} job(s)!</b>", cancelled)),
+            )
+            .await?;
+    }
+    return Ok(());
+}
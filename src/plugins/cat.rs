@@ -4,21 +4,89 @@
 //! SPDX-License-Identifier: MIT
 //!
 
-// Description: Sends cat pic according to the HTTP status code.
-
-use grammers_client::{
-    types::{InputMessage, Message},
-    Client,
+use crate::app::AppContext;
+use crate::plugins::{
+    args::{Args, Kind, Param},
+    error::BotError,
+    inline::{Inline, InlineFuture, InlineResult},
+    BoxFuture, Category, Context, Plugin,
 };
+use crate::transport::{Incoming, Outgoing};
+use std::sync::Arc;
+
+type Result = std::result::Result<(), BotError>;
 
-type Result = std::result::Result<(), Box<dyn std::error::Error>>;
+pub struct Cat;
+
+impl Plugin for Cat {
+    fn name(&self) -> &'static str {
+        "cat"
+    }
 
-pub async fn knightcmd_cat(client: Client, message: Message, mut kat: i64) -> Result {
-    if kat == 0 {
-        kat = 404;
+    fn description(&self) -> &'static str {
+        "Sends cat pic according to the HTTP status code."
     }
-    let url = format!("https://httpcats.com/{}.jpg", kat);
-    let photo = InputMessage::text("").photo_url(url);
-    client.send_message(message.chat(), photo).await?;
+
+    fn category(&self) -> Category {
+        Category::Fun
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["418"]
+    }
+
+    fn params(&self) -> &'static [Param] {
+        const PARAMS: &[Param] = &[Param::optional("code", Kind::Int(100, 599))];
+        PARAMS
+    }
+
+    fn inline(&self) -> Option<&dyn Inline> {
+        Some(self)
+    }
+
+    fn run(&self, ctx: Context) -> BoxFuture {
+        Box::pin(knightcmd_cat(
+            ctx.app,
+            ctx.message,
+            ctx.parsed.int("code").unwrap_or(404),
+        ))
+    }
+}
+
+impl Inline for Cat {
+    fn answer(&self, _app: Arc<AppContext>, args: Args) -> InlineFuture {
+        let code = args.int("code").unwrap_or(404);
+        let result = InlineResult::new(format!("HTTP {}", code), photo(code));
+        Box::pin(async move { Ok(vec![result]) })
+    }
+}
+
+fn photo(code: i64) -> Outgoing {
+    Outgoing::text("").photo_url(format!("https://httpcats.com/{}.jpg", code))
+}
+
+pub async fn knightcmd_cat(app: Arc<AppContext>, message: Incoming, kat: i64) -> Result {
+    app.transport.send(message.chat(), photo(kat)).await?;
     return Ok(());
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, Event, TestBot};
+
+    #[tokio::test]
+    async fn sends_the_cat_for_the_code() {
+        let bot = TestBot::new();
+        knightcmd_cat(bot.app(), testing::message(1, "/cat 418"), 418)
+            .await
+            .unwrap();
+        assert_eq!(
+            bot.take(),
+            [Event::Sent {
+                chat: testing::CHAT_ID,
+                outgoing: Outgoing::text("").photo_url("https://httpcats.com/418.jpg"),
+            }]
+        );
+    }
+}
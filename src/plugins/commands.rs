@@ -0,0 +1,65 @@
+//!
+//! Copyright (C) 2023-2025 cyberknight777
+//!
+//! SPDX-License-Identifier: MIT
+//!
+
+use crate::app::AppContext;
+use crate::cfg::Role;
+use crate::menu;
+use crate::plugins::{error::BotError, html::Html, BoxFuture, Context, Plugin};
+use crate::transport::{Incoming, Outgoing};
+use std::sync::Arc;
+
+type Result = std::result::Result<(), BotError>;
+
+pub struct Commands;
+
+impl Plugin for Commands {
+    fn name(&self) -> &'static str {
+        "commands"
+    }
+
+    fn description(&self) -> &'static str {
+        "Publishes the command menu to Telegram again."
+    }
+
+    fn role(&self) -> Role {
+        Role::Admin
+    }
+
+    fn run(&self, ctx: Context) -> BoxFuture {
+        Box::pin(knightcmd_commands(ctx.app, ctx.message))
+    }
+}
+
+pub async fn knightcmd_commands(app: Arc<AppContext>, message: Incoming) -> Result {
+    let Some(client) = app.transport.client() else {
+        return Err(BotError::bad_input(
+            "There is no command menu outside of Telegram.",
+        ));
+    };
+    // Whoever asks is the one user we surely have an access hash for.
+    let known = message.chat().packed();
+    let published = menu::publish(&app, &client, known.as_slice()).await?;
+    let mut text = Html::new()
+        .bold("Command menu published!")
+        .line()
+        .field("Public", published.public.to_string())
+        .field("Chat admins", published.chat_admins.to_string())
+        .field("Users with their own menu", published.users.to_string());
+    if !published.failed.is_empty() {
+        let failed = published
+            .failed
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>();
+        text = text
+            .field("Failed", failed.join(", "))
+            .italic("They may have to message me first.");
+    }
+    app.transport
+        .reply(&message, Outgoing::html(text.build()))
+        .await?;
+    return Ok(());
+}
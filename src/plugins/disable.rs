@@ -0,0 +1,121 @@
+//!
+//! Copyright (C) 2023-2025 cyberknight777
+//!
+//! SPDX-License-Identifier: MIT
+//!
+
+use crate::app::AppContext;
+use crate::cfg::Role;
+use crate::plugins::{
+    self,
+    args::{Kind, Param},
+    error::BotError,
+    html::Html,
+    BoxFuture, Context, Plugin,
+};
+use crate::transport::{Incoming, Outgoing};
+use std::sync::Arc;
+
+type Result = std::result::Result<(), BotError>;
+
+pub struct Disable;
+
+impl Plugin for Disable {
+    fn name(&self) -> &'static str {
+        "disable"
+    }
+
+    fn description(&self) -> &'static str {
+        "Turns commands off in this chat."
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["rtfm anyone"]
+    }
+
+    fn params(&self) -> &'static [Param] {
+        const PARAMS: &[Param] = &[Param::required("commands", Kind::Rest)];
+        PARAMS
+    }
+
+    fn role(&self) -> Role {
+        Role::ChatAdmin
+    }
+
+    fn run(&self, ctx: Context) -> BoxFuture {
+        Box::pin(knightcmd_disable(
+            ctx.app,
+            ctx.message,
+            ctx.prefix,
+            ctx.parsed.get("commands").unwrap_or_default().to_owned(),
+        ))
+    }
+}
+
+pub async fn knightcmd_disable(
+    app: Arc<AppContext>,
+    message: Incoming,
+    prefix: String,
+    commands: String,
+) -> Result {
+    let names = resolve(&commands, &prefix)?;
+    app.store
+        .update_chat(message.chat().id(), |settings| {
+            settings
+                .disabled
+                .extend(names.iter().map(|name| name.to_string()))
+        })
+        .await?;
+    app.transport
+        .reply(
+            &message,
+            Outgoing::html(
+                Html::new()
+                    .bold(format!("Disabled {} in this chat!", list(&names, &prefix)))
+                    .build(),
+            ),
+        )
+        .await?;
+    return Ok(());
+}
+
+/// Looks up the commands named in `commands`, which may be written with or
+/// without `prefix`, and returns their canonical names.
+///
+/// Admin commands and those for turning commands on and off can't be
+/// toggled.
+pub fn resolve(commands: &str, prefix: &str) -> std::result::Result<Vec<&'static str>, BotError> {
+    let mut names = Vec::new();
+    for name in commands.split_whitespace() {
+        let name = name.strip_prefix(prefix).unwrap_or(name);
+        let plugin = match plugins::find(name, false) {
+            Some(plugin) => plugin,
+            None => {
+                return Err(BotError::bad_input(format!(
+                    "There is no {}{} command.",
+                    prefix, name
+                )))
+            }
+        };
+        if plugin.role() == Role::ChatAdmin {
+            return Err(BotError::bad_input(format!(
+                "{}{} can't be turned off.",
+                prefix,
+                plugin.name()
+            )));
+        }
+        if !names.contains(&plugin.name()) {
+            names.push(plugin.name());
+        }
+    }
+    Ok(names)
+}
+
+/// Formats command names for a reply, e.g. `/cat, /dog`.
+pub fn list<S: AsRef<str>>(names: &[S], prefix: &str) -> String {
+    names
+        .iter()
+        .map(|name| format!("{}{}", prefix, name.as_ref()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
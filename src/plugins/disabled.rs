@@ -0,0 +1,54 @@
+//!
+//! Copyright (C) 2023-2025 cyberknight777
+//!
+//! SPDX-License-Identifier: MIT
+//!
+
+use crate::app::AppContext;
+use crate::cfg::Role;
+use crate::plugins::{disable::list, error::BotError, html::Html, BoxFuture, Context, Plugin};
+use crate::transport::{Incoming, Outgoing};
+use std::sync::Arc;
+
+type Result = std::result::Result<(), BotError>;
+
+pub struct Disabled;
+
+impl Plugin for Disabled {
+    fn name(&self) -> &'static str {
+        "disabled"
+    }
+
+    fn description(&self) -> &'static str {
+        "Lists the commands turned off in this chat."
+    }
+
+    fn role(&self) -> Role {
+        Role::ChatAdmin
+    }
+
+    fn run(&self, ctx: Context) -> BoxFuture {
+        Box::pin(knightcmd_disabled(ctx.app, ctx.message, ctx.prefix))
+    }
+}
+
+pub async fn knightcmd_disabled(app: Arc<AppContext>, message: Incoming, prefix: String) -> Result {
+    let disabled = app
+        .store
+        .chat(message.chat().id())
+        .disabled
+        .into_iter()
+        .collect::<Vec<_>>();
+    let text = if disabled.is_empty() {
+        Html::new().bold("All commands are enabled in this chat!")
+    } else {
+        Html::new()
+            .bold("Disabled in this chat:")
+            .text(" ")
+            .text(list(&disabled, &prefix))
+    };
+    app.transport
+        .reply(&message, Outgoing::html(text.build()))
+        .await?;
+    return Ok(());
+}
@@ -4,21 +4,53 @@
 //! SPDX-License-Identifier: MIT
 //!
 
-// Description: Sends dog pic according to the HTTP status code.
-
-use grammers_client::{
-    types::{InputMessage, Message},
-    Client,
+use crate::app::AppContext;
+use crate::plugins::{
+    args::{Kind, Param},
+    error::BotError,
+    BoxFuture, Category, Context, Plugin,
 };
+use crate::transport::{Incoming, Outgoing};
+use std::sync::Arc;
+
+type Result = std::result::Result<(), BotError>;
+
+pub struct Dog;
+
+impl Plugin for Dog {
+    fn name(&self) -> &'static str {
+        "dog"
+    }
+
+    fn description(&self) -> &'static str {
+        "Sends dog pic according to the HTTP status code."
+    }
 
-type Result = std::result::Result<(), Box<dyn std::error::Error>>;
+    fn category(&self) -> Category {
+        Category::Fun
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["404"]
+    }
 
-pub async fn knightcmd_dog(client: Client, message: Message, mut doge: i64) -> Result {
-    if doge == 0 {
-        doge = 404;
+    fn params(&self) -> &'static [Param] {
+        const PARAMS: &[Param] = &[Param::optional("code", Kind::Int(100, 599))];
+        PARAMS
     }
+
+    fn run(&self, ctx: Context) -> BoxFuture {
+        Box::pin(knightcmd_dog(
+            ctx.app,
+            ctx.message,
+            ctx.parsed.int("code").unwrap_or(404),
+        ))
+    }
+}
+
+pub async fn knightcmd_dog(app: Arc<AppContext>, message: Incoming, doge: i64) -> Result {
     let url = format!("https://http.dog/{}.jpg", doge);
-    let photo = InputMessage::text("").photo_url(url);
-    client.send_message(message.chat(), photo).await?;
+    let photo = Outgoing::text("").photo_url(url);
+    app.transport.send(message.chat(), photo).await?;
     return Ok(());
 }
@@ -4,17 +4,34 @@
 //! SPDX-License-Identifier: MIT
 //!
 
-// Description: Rolls an eightball to say yes or no.
+use crate::app::AppContext;
+use crate::plugins::{self, error::BotError, BoxFuture, Category, Context, Plugin};
+use crate::transport::{Incoming, Outgoing};
+use std::sync::Arc;
 
-use crate::plugins;
-use grammers_client::{
-    types::{InputMessage, Message},
-    Client,
-};
+type Result = std::result::Result<(), BotError>;
 
-type Result = std::result::Result<(), Box<dyn std::error::Error>>;
+pub struct EightBall;
 
-pub async fn knightcmd_eightball(client: Client, message: Message) -> Result {
+impl Plugin for EightBall {
+    fn name(&self) -> &'static str {
+        "eightball"
+    }
+
+    fn description(&self) -> &'static str {
+        "Rolls an eightball to say yes or no."
+    }
+
+    fn category(&self) -> Category {
+        Category::Fun
+    }
+
+    fn run(&self, ctx: Context) -> BoxFuture {
+        Box::pin(knightcmd_eightball(ctx.app, ctx.message))
+    }
+}
+
+pub async fn knightcmd_eightball(app: Arc<AppContext>, message: Incoming) -> Result {
     let ball = plugins::random(2);
     let result = if ball == 0 {
         "Yes, it is the truth!"
@@ -22,14 +39,11 @@ pub async fn knightcmd_eightball(client: Client, message: Message) -> Result {
         "No, this is a prepostrous lie!"
     };
     if let Some(id) = message.reply_to_message_id() {
-        client
-            .send_message(
-                message.chat(),
-                InputMessage::text(result).reply_to(Some(id)),
-            )
+        app.transport
+            .send(message.chat(), Outgoing::text(result).reply_to(Some(id)))
             .await?;
     } else {
-        message.reply(result).await?;
+        app.transport.reply(&message, result.into()).await?;
     }
     return Ok(());
 }
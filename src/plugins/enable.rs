@@ -0,0 +1,80 @@
+//!
+//! Copyright (C) 2023-2025 cyberknight777
+//!
+//! SPDX-License-Identifier: MIT
+//!
+
+use crate::app::AppContext;
+use crate::cfg::Role;
+use crate::plugins::{
+    args::{Kind, Param},
+    disable::{list, resolve},
+    error::BotError,
+    html::Html,
+    BoxFuture, Context, Plugin,
+};
+use crate::transport::{Incoming, Outgoing};
+use std::sync::Arc;
+
+type Result = std::result::Result<(), BotError>;
+
+pub struct Enable;
+
+impl Plugin for Enable {
+    fn name(&self) -> &'static str {
+        "enable"
+    }
+
+    fn description(&self) -> &'static str {
+        "Turns disabled commands back on in this chat."
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["rtfm"]
+    }
+
+    fn params(&self) -> &'static [Param] {
+        const PARAMS: &[Param] = &[Param::required("commands", Kind::Rest)];
+        PARAMS
+    }
+
+    fn role(&self) -> Role {
+        Role::ChatAdmin
+    }
+
+    fn run(&self, ctx: Context) -> BoxFuture {
+        Box::pin(knightcmd_enable(
+            ctx.app,
+            ctx.message,
+            ctx.prefix,
+            ctx.parsed.get("commands").unwrap_or_default().to_owned(),
+        ))
+    }
+}
+
+pub async fn knightcmd_enable(
+    app: Arc<AppContext>,
+    message: Incoming,
+    prefix: String,
+    commands: String,
+) -> Result {
+    let names = resolve(&commands, &prefix)?;
+    app.store
+        .update_chat(message.chat().id(), |settings| {
+            for name in &names {
+                settings.disabled.remove(*name);
+            }
+        })
+        .await?;
+    app.transport
+        .reply(
+            &message,
+            Outgoing::html(
+                Html::new()
+                    .bold(format!("Enabled {} in this chat!", list(&names, &prefix)))
+                    .build(),
+            ),
+        )
+        .await?;
+    return Ok(());
+}
@@ -0,0 +1,126 @@
+//!
+//! Copyright (C) 2023-2025 cyberknight777
+//!
+//! SPDX-License-Identifier: MIT
+//!
+
+use crate::cfg::Role;
+use crate::plugins::req::ReqError;
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::error::Error;
+use std::fmt;
+
+/// Why a command failed, deciding what the user gets told.
+///
+/// Any error can be turned into a `BotError` with `?`; the ones we know
+/// about land in their own variant, everything else is [`BotError::Internal`].
+#[derive(Debug)]
+pub enum BotError {
+    /// The user sent something we can't work with; the text is shown as is.
+    BadInput(String),
+    /// An upstream API failed.
+    Upstream(ReqError),
+    /// The user's role is too low for the command.
+    PermissionDenied(Role),
+    /// Something took too long.
+    Timeout,
+    /// A bug or a Telegram failure; details only go to the log and admins.
+    ///
+    /// The backtrace is only captured if `RUST_BACKTRACE` is set.
+    Internal(Box<dyn Error + Send + Sync>, Backtrace),
+}
+
+impl BotError {
+    pub fn bad_input(text: impl Into<String>) -> Self {
+        BotError::BadInput(text.into())
+    }
+
+    /// Whether this is our (or an upstream) fault, worth an error ID and a
+    /// report to the admins, rather than the user's.
+    pub fn is_failure(&self) -> bool {
+        matches!(
+            self,
+            BotError::Upstream(_) | BotError::Timeout | BotError::Internal(..)
+        )
+    }
+
+    /// Short name of the variant, for summaries.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            BotError::BadInput(_) => "bad input",
+            BotError::Upstream(_) => "upstream",
+            BotError::PermissionDenied(_) => "permission denied",
+            BotError::Timeout => "timeout",
+            BotError::Internal(..) => "internal",
+        }
+    }
+
+    /// The error followed by everything that caused it.
+    pub fn chain(&self) -> Vec<String> {
+        let mut chain = vec![self.to_string()];
+        let mut source = match self {
+            BotError::Upstream(e) => e.source(),
+            BotError::Internal(e, _) => e.source(),
+            _ => None,
+        };
+        while let Some(e) = source {
+            chain.push(e.to_string());
+            source = e.source();
+        }
+        chain
+    }
+
+    pub fn backtrace(&self) -> Option<&Backtrace> {
+        match self {
+            BotError::Internal(_, backtrace) if backtrace.status() == BacktraceStatus::Captured => {
+                Some(backtrace)
+            }
+            _ => None,
+        }
+    }
+
+    /// A short explanation for the user who ran `command`.
+    pub fn user_message(&self, command: &str) -> String {
+        match self {
+            BotError::BadInput(text) => text.clone(),
+            BotError::Upstream(e) => format!("{} couldn't get an answer, {}.", command, e),
+            BotError::PermissionDenied(role) => {
+                format!("Sorry, {} is only available to {}s.", command, role)
+            }
+            BotError::Timeout => format!("{} took too long, please try again later.", command),
+            BotError::Internal(..) => {
+                format!("{} failed, something went wrong on my side.", command)
+            }
+        }
+    }
+}
+
+impl fmt::Display for BotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BotError::BadInput(text) => write!(f, "bad input: {}", text),
+            BotError::Upstream(e) => write!(f, "upstream failure: {}", e),
+            BotError::PermissionDenied(role) => write!(f, "permission denied, needs {}", role),
+            BotError::Timeout => f.write_str("timed out"),
+            BotError::Internal(e, _) => write!(f, "internal error: {}", e),
+        }
+    }
+}
+
+impl<E: Error + Send + Sync + 'static> From<E> for BotError {
+    fn from(e: E) -> Self {
+        let e: Box<dyn Error + Send + Sync> = Box::new(e);
+        let e = match e.downcast::<ReqError>() {
+            Ok(e) => return BotError::Upstream(*e),
+            Err(e) => e,
+        };
+        let e = match e.downcast::<reqwest::Error>() {
+            Ok(e) => return BotError::Upstream(ReqError::Network(*e)),
+            Err(e) => e,
+        };
+        if e.is::<tokio::time::error::Elapsed>() {
+            return BotError::Timeout;
+        }
+        BotError::Internal(e, Backtrace::capture())
+    }
+}
@@ -4,28 +4,42 @@
 //! SPDX-License-Identifier: MIT
 //!
 
-// Description: Flips a coin to say heads or tails.
+use crate::app::AppContext;
+use crate::plugins::{self, error::BotError, BoxFuture, Category, Context, Plugin};
+use crate::transport::{Incoming, Outgoing};
+use std::sync::Arc;
 
-use crate::plugins;
-use grammers_client::{
-    types::{InputMessage, Message},
-    Client,
-};
+type Result = std::result::Result<(), BotError>;
 
-type Result = std::result::Result<(), Box<dyn std::error::Error>>;
+pub struct FlipCoin;
 
-pub async fn knightcmd_flipcoin(client: Client, message: Message) -> Result {
+impl Plugin for FlipCoin {
+    fn name(&self) -> &'static str {
+        "flipcoin"
+    }
+
+    fn description(&self) -> &'static str {
+        "Flips a coin to say heads or tails."
+    }
+
+    fn category(&self) -> Category {
+        Category::Fun
+    }
+
+    fn run(&self, ctx: Context) -> BoxFuture {
+        Box::pin(knightcmd_flipcoin(ctx.app, ctx.message))
+    }
+}
+
+pub async fn knightcmd_flipcoin(app: Arc<AppContext>, message: Incoming) -> Result {
     let coin = plugins::random(2);
     let result = if coin == 0 { "Heads!" } else { "Tails!" };
     if let Some(id) = message.reply_to_message_id() {
-        client
-            .send_message(
-                message.chat(),
-                InputMessage::text(result).reply_to(Some(id)),
-            )
+        app.transport
+            .send(message.chat(), Outgoing::text(result).reply_to(Some(id)))
             .await?;
     } else {
-        message.reply(result).await?;
+        app.transport.reply(&message, result.into()).await?;
     }
     return Ok(());
 }
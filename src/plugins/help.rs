@@ -4,76 +4,261 @@
 //! SPDX-License-Identifier: MIT
 //!
 
-// Description: Displays this text.
+use crate::app::AppContext;
+use crate::cfg::Role;
+use crate::plugins::{
+    self,
+    args::{self, Kind, Param},
+    error::BotError,
+    html::Html,
+    BoxFuture, Category, Context, Plugin,
+};
+use crate::transport::{Incoming, Outgoing};
+use std::sync::Arc;
 
-use grammers_client::types::Message;
-use std::fs;
-use std::io::{self, BufRead};
-use std::result::Result;
+type Result = std::result::Result<(), BotError>;
 
-struct CommandInfo {
-    name: String,
-    description: String,
+pub struct Help;
+
+impl Plugin for Help {
+    fn name(&self) -> &'static str {
+        "help"
+    }
+
+    fn description(&self) -> &'static str {
+        "Lists my commands, or explains one of them."
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["whois"]
+    }
+
+    fn params(&self) -> &'static [Param] {
+        const PARAMS: &[Param] = &[Param::optional("command", Kind::Word)];
+        PARAMS
+    }
+
+    fn run(&self, ctx: Context) -> BoxFuture {
+        Box::pin(knightcmd_help(
+            ctx.app,
+            ctx.message,
+            ctx.prefix,
+            ctx.parsed.get("command").map(str::to_owned),
+        ))
+    }
 }
 
-pub async fn knightcmd_help(message: Message) -> Result<(), Box<dyn std::error::Error>> {
-    let mut commands = Vec::new();
-
-    let plugin_dir = "src/plugins";
-    for entry in fs::read_dir(plugin_dir)? {
-        if let Ok(entry) = entry {
-            if let Some(filename) = entry.file_name().to_str() {
-                if filename.ends_with(".rs")
-                    && filename != "mod.rs"
-                    && filename != "req.rs"
-                    && filename != "sh.rs"
-                {
-                    let command_name = filename.trim_end_matches(".rs").to_string();
-                    let description = get_command_description(&command_name, plugin_dir)?;
-
-                    commands.push(CommandInfo {
-                        name: command_name,
-                        description,
-                    });
-                }
-            }
+pub async fn knightcmd_help(
+    app: Arc<AppContext>,
+    message: Incoming,
+    prefix: String,
+    command: Option<String>,
+) -> Result {
+    let config = app.config();
+    let admin_prefix = &config.prefixes.admin;
+    let text = match command {
+        Some(command) => {
+            let plugin = if let Some(name) = command.strip_prefix(admin_prefix.as_str()) {
+                plugins::find(name, true)
+            } else {
+                let name = command.strip_prefix(prefix.as_str()).unwrap_or(&command);
+                plugins::find(name, false).or_else(|| plugins::find(name, true))
+            };
+            let plugin = plugin
+                .ok_or_else(|| BotError::bad_input(format!("There is no {} command.", command)))?;
+            let prefix = if plugins::is_admin_command(plugin) {
+                admin_prefix
+            } else {
+                &prefix
+            };
+            details(plugin, prefix)
         }
-    }
+        None => {
+            let role = message
+                .sender()
+                .map_or(Role::User, |sender| config.role_of(sender.id()));
+            listing(&prefix, admin_prefix, role >= Role::Admin)
+        }
+    };
+
+    app.transport
+        .reply(&message, Outgoing::html(text.build()))
+        .await?;
 
-    commands.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(())
+}
 
-    let mut help_msg = "Hello There!, I am a bot made by cyberknight777 in Rust based on gramme.rs.\nHere's a list of my commands (sorted alphabetically):\n".to_owned();
+/// Every public command by category, and admin commands if `admin` is set.
+fn listing(prefix: &str, admin_prefix: &str, admin: bool) -> Html {
+    let mut commands = plugins::REGISTRY
+        .iter()
+        .copied()
+        .filter(|p| !plugins::is_admin_command(*p))
+        .collect::<Vec<_>>();
+    commands.sort_by_key(|p| (p.category(), p.name()));
+
+    let mut text = Html::new()
+        .text("Hello There!, I am a bot made by cyberknight777 in Rust based on gramme.rs.")
+        .line()
+        .text(format!(
+            "Here's a list of my commands, send {}help <command> to learn more about one:",
+            prefix
+        ))
+        .line();
+    let mut category = None;
     for command in commands {
-        help_msg.push_str(&format!(
-            "/{name} - {description}\n",
-            name = command.name,
-            description = command.description
-        ));
+        if category != Some(command.category()) {
+            category = Some(command.category());
+            text = text.line().bold(command.category().to_string()).line();
+        }
+        text = entry(text, command, prefix);
     }
 
-    message.reply(help_msg).await?;
+    if admin {
+        let mut commands = plugins::REGISTRY
+            .iter()
+            .copied()
+            .filter(|p| plugins::is_admin_command(*p))
+            .collect::<Vec<_>>();
+        commands.sort_by_key(|p| p.name());
+        text = text.line().bold("Admin").line();
+        for command in commands {
+            text = entry(text, command, admin_prefix);
+        }
+    }
+    text
+}
 
-    Ok(())
+fn entry(text: Html, command: &dyn Plugin, prefix: &str) -> Html {
+    text.text(format!(
+        "{}{} - {}",
+        prefix,
+        command.name(),
+        command.description()
+    ))
+    .line()
 }
 
-fn get_command_description(command_name: &str, plugin_dir: &str) -> Result<String, io::Error> {
-    let file_path = format!("{}/{}.rs", plugin_dir, command_name);
-    let file = fs::File::open(file_path)?;
-
-    let reader = io::BufReader::new(file);
-    let mut description = String::new();
-
-    for line in reader.lines() {
-        if let Ok(line) = line {
-            if line.starts_with("// Description:") {
-                description = line
-                    .trim_start_matches("// Description:")
-                    .trim()
-                    .to_string();
-                break;
-            }
+/// Usage, examples and restrictions of a single command.
+fn details(plugin: &dyn Plugin, prefix: &str) -> Html {
+    let usage = format!("{}{} {}", prefix, plugin.name(), args::usage(plugin));
+    let mut text = Html::new()
+        .code(usage.trim_end())
+        .line()
+        .text(plugin.description())
+        .line()
+        .line()
+        .field("Category", plugin.category().to_string());
+    if !plugin.aliases().is_empty() {
+        let aliases = plugin
+            .aliases()
+            .iter()
+            .map(|alias| format!("{}{}", prefix, alias))
+            .collect::<Vec<_>>();
+        text = text.field("Aliases", aliases.join(", "));
+    }
+    if plugin.role() > Role::User {
+        text = text.field("Only for", format!("{}s", plugin.role()));
+    }
+    if !plugin.examples().is_empty() {
+        text = text.line().bold("Examples").line();
+        for example in plugin.examples() {
+            text = text
+                .code(format!("{}{} {}", prefix, plugin.name(), example))
+                .line();
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfg::Config;
+    use crate::testing::{self, TestBot, USER_ID};
+
+    async fn help(bot: &TestBot, command: Option<&str>) -> Result {
+        let message = testing::message(1, "/help");
+        knightcmd_help(
+            bot.app(),
+            message,
+            "/".to_owned(),
+            command.map(str::to_owned),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn explains_a_command() {
+        let bot = TestBot::new();
+        help(&bot, Some("whois")).await.unwrap();
+        assert_eq!(
+            bot.take_sent(),
+            Outgoing::html(
+                "<code>/whois [site] [--full]</code>\n\
+                 Checks WHOIS information of a given URL.\n\
+                 \n\
+                 <b>Category</b>: Network\n\
+                 \n\
+                 <b>Examples</b>\n\
+                 <code>/whois example.com</code>\n\
+                 <code>/whois --full example.com</code>\n"
+            )
+            .reply_to(Some(1))
+        );
+    }
+
+    #[tokio::test]
+    async fn explains_admin_commands_with_their_prefix() {
+        let bot = TestBot::new();
+        help(&bot, Some("k.sh")).await.unwrap();
+        let expected = Outgoing::html(
+            "<code>k.sh [command...]</code>\n\
+             Executes a shell command, reporting progress as it runs.\n\
+             \n\
+             <b>Category</b>: Dev tools\n\
+             <b>Only for</b>: owners\n\
+             \n\
+             <b>Examples</b>\n\
+             <code>k.sh uname -a</code>\n",
+        )
+        .reply_to(Some(1));
+        assert_eq!(bot.take_sent(), expected);
+
+        // The prefix may be left out.
+        help(&bot, Some("sh")).await.unwrap();
+        assert_eq!(bot.take_sent(), expected);
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_commands() {
+        let bot = TestBot::new();
+        match help(&bot, Some("nope")).await {
+            Err(BotError::BadInput(text)) => assert_eq!(text, "There is no nope command."),
+            result => panic!(
+                "expected bad input, got {:?}",
+                result.map_err(|e| e.to_string())
+            ),
         }
+        assert!(bot.take().is_empty());
     }
 
-    Ok(description)
+    #[tokio::test]
+    async fn lists_admin_commands_only_to_admins() {
+        let bot = TestBot::new();
+        help(&bot, None).await.unwrap();
+        let text = bot.take_sent().text;
+        assert!(text.contains("<b>Network</b>\n"));
+        assert!(text.contains("/whois - Checks WHOIS information of a given URL.\n"));
+        assert!(!text.contains("<b>Admin</b>"));
+        assert!(!text.contains("k.sh - "));
+
+        let mut config = Config::default();
+        config.admins = vec![USER_ID];
+        let bot = TestBot::with_config(config);
+        help(&bot, None).await.unwrap();
+        let text = bot.take_sent().text;
+        assert!(text.contains("<b>Admin</b>\n"));
+        assert!(text.contains("k.sh - Executes a shell command, reporting progress as it runs.\n"));
+    }
 }
@@ -0,0 +1,186 @@
+//!
+//! Copyright (C) 2023-2025 cyberknight777
+//!
+//! SPDX-License-Identifier: MIT
+//!
+
+use std::fmt;
+
+/// Escapes `text` so it shows up verbatim in an HTML reply.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Turns HTML back into plain text by dropping tags and undoing [`escape`].
+pub fn to_plain(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&amp;", "&")
+}
+
+/// Builds an HTML reply out of fragments, escaping everything that is not markup.
+///
+/// ```ignore
+/// let text = Html::new().bold("Name").text(": ").code(name).build();
+/// ```
+#[derive(Default)]
+pub struct Html(String);
+
+impl Html {
+    pub fn new() -> Self {
+        Html(String::new())
+    }
+
+    /// Plain text.
+    pub fn text(mut self, text: impl AsRef<str>) -> Self {
+        self.0.push_str(&escape(text.as_ref()));
+        self
+    }
+
+    /// Markup that is trusted as is.
+    pub fn raw(mut self, html: impl AsRef<str>) -> Self {
+        self.0.push_str(html.as_ref());
+        self
+    }
+
+    pub fn bold(self, text: impl AsRef<str>) -> Self {
+        self.tag("b", text.as_ref())
+    }
+
+    pub fn italic(self, text: impl AsRef<str>) -> Self {
+        self.tag("i", text.as_ref())
+    }
+
+    pub fn code(self, text: impl AsRef<str>) -> Self {
+        self.tag("code", text.as_ref())
+    }
+
+    pub fn link(mut self, text: impl AsRef<str>, url: impl AsRef<str>) -> Self {
+        self.0.push_str(&format!(
+            "<a href=\"{}\">{}</a>",
+            escape(url.as_ref()),
+            escape(text.as_ref())
+        ));
+        self
+    }
+
+    /// A `<b>label</b>: value` line.
+    pub fn field(self, label: impl AsRef<str>, value: impl AsRef<str>) -> Self {
+        self.bold(label).text(": ").text(value).line()
+    }
+
+    pub fn line(mut self) -> Self {
+        self.0.push('\n');
+        self
+    }
+
+    pub fn build(self) -> String {
+        self.0
+    }
+
+    fn tag(mut self, tag: &str, text: &str) -> Self {
+        self.0
+            .push_str(&format!("<{tag}>{}</{tag}>", escape(text), tag = tag));
+        self
+    }
+}
+
+impl fmt::Display for Html {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<Html> for String {
+    fn from(html: Html) -> Self {
+        html.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOSTILE: &str = "<b>x</b> & \"y\" <a href='z'>";
+
+    #[test]
+    fn escape_neutralizes_markup() {
+        assert_eq!(
+            escape(HOSTILE),
+            "&lt;b&gt;x&lt;/b&gt; &amp; &quot;y&quot; &lt;a href='z'&gt;"
+        );
+        assert_eq!(escape("&lt;"), "&amp;lt;");
+        assert_eq!(escape("plain ünïcode"), "plain ünïcode");
+    }
+
+    #[test]
+    fn formatters_escape_their_content() {
+        for html in [
+            Html::new().text(HOSTILE).build(),
+            Html::new().bold(HOSTILE).build(),
+            Html::new().italic(HOSTILE).build(),
+            Html::new().code(HOSTILE).build(),
+            Html::new().field(HOSTILE, HOSTILE).build(),
+        ] {
+            assert!(!html.contains("<b>x"), "{}", html);
+            assert!(!html.contains("<a "), "{}", html);
+            assert!(html.contains("&lt;b&gt;x&lt;/b&gt;"), "{}", html);
+        }
+    }
+
+    #[test]
+    fn tags_wrap_escaped_text() {
+        assert_eq!(Html::new().bold("a<b").build(), "<b>a&lt;b</b>");
+        assert_eq!(Html::new().code("1 & 2").build(), "<code>1 &amp; 2</code>");
+        assert_eq!(
+            Html::new().field("Name", "<yay>").build(),
+            "<b>Name</b>: &lt;yay&gt;\n"
+        );
+    }
+
+    #[test]
+    fn link_escapes_url_and_text() {
+        assert_eq!(
+            Html::new()
+                .link("</a><b>", "https://x.y/?a=1&b=\"><script>")
+                .build(),
+            "<a href=\"https://x.y/?a=1&amp;b=&quot;&gt;&lt;script&gt;\">&lt;/a&gt;&lt;b&gt;</a>"
+        );
+    }
+
+    #[test]
+    fn raw_is_kept_verbatim() {
+        assert_eq!(
+            Html::new().raw("<b>").text("<").raw("</b>").build(),
+            "<b>&lt;</b>"
+        );
+    }
+
+    #[test]
+    fn to_plain_round_trips() {
+        let html = Html::new().bold("Name").text(": ").code(HOSTILE).build();
+        assert_eq!(to_plain(&html), format!("Name: {}", HOSTILE));
+        assert_eq!(to_plain(&escape("&amp;")), "&amp;");
+    }
+}
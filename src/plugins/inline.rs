@@ -0,0 +1,233 @@
+//!
+//! Copyright (C) 2023-2025 cyberknight777
+//!
+//! SPDX-License-Identifier: MIT
+//!
+
+//! Inline queries, `@bot <command> <args>` typed in any chat, answered with
+//! results built by the same code as the command.
+
+use crate::app::AppContext;
+use crate::cfg::Role;
+use crate::limits::Verdict;
+use crate::plugins::{args, error::BotError, find, Plugin};
+use crate::transport::Outgoing;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Future returned by [`Inline::answer`].
+pub type InlineFuture = Pin<Box<dyn Future<Output = Result<Vec<InlineResult>, BotError>> + Send>>;
+
+/// A command that can also answer inline queries.
+pub trait Inline: Sync {
+    /// Results for `args`, parsed according to [`Plugin::params`].
+    fn answer(&self, app: Arc<AppContext>, args: args::Args) -> InlineFuture;
+}
+
+/// One result offered for an inline query.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InlineResult {
+    pub title: String,
+    pub description: String,
+    /// Sent to the chat when the result is picked; a result with a photo is
+    /// shown as that photo, anything else as an article.
+    pub message: Outgoing,
+}
+
+impl InlineResult {
+    pub fn new(title: impl Into<String>, message: Outgoing) -> Self {
+        InlineResult {
+            title: title.into(),
+            description: String::new(),
+            message,
+        }
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+}
+
+/// Most queries kept in [`InlineCache`]; every keystroke is a new query.
+const CACHE_CAPACITY: usize = 512;
+
+/// Results of recent inline queries, so typing the same query again does not
+/// hit the upstream API.
+#[derive(Default)]
+pub struct InlineCache {
+    entries: Mutex<HashMap<String, (Instant, Vec<InlineResult>)>>,
+}
+
+impl InlineCache {
+    fn get(&self, query: &str, ttl: Duration) -> Option<Vec<InlineResult>> {
+        let entries = self.entries.lock().unwrap();
+        let (cached, results) = entries.get(query)?;
+        (cached.elapsed() < ttl).then(|| results.clone())
+    }
+
+    fn insert(&self, query: String, results: Vec<InlineResult>, ttl: Duration) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, (cached, _)| cached.elapsed() < ttl);
+        if entries.len() >= CACHE_CAPACITY {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, (cached, _))| *cached)
+                .map(|(query, _)| query.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(query, (Instant::now(), results));
+    }
+}
+
+/// Finds the command an inline query is for, if it may answer inline.
+fn plugin_for(app: &AppContext, name: &str) -> Option<(&'static dyn Plugin, &'static dyn Inline)> {
+    let config = app.config();
+    if !config.inline.enabled || !config.inline.plugins.iter().any(|p| p == name) {
+        return None;
+    }
+    // Inline results end up in chats the bot can't see, so only commands
+    // anyone may run are offered.
+    let plugin = find(name, false).filter(|plugin| plugin.role() == Role::User)?;
+    Some((plugin, plugin.inline()?))
+}
+
+/// Results for inline query `query` sent by `user`; nothing if it does not
+/// name an inline command.
+pub async fn answer(app: &Arc<AppContext>, user: i64, query: &str) -> Vec<InlineResult> {
+    let query = query.trim();
+    let (name, rest) = match query.find(char::is_whitespace) {
+        Some(at) => (&query[..at], query[at..].trim_start()),
+        None => (query, ""),
+    };
+    let (plugin, inline) = match plugin_for(app, name) {
+        Some(found) => found,
+        None => return Vec::new(),
+    };
+
+    let key = format!("{} {}", plugin.name(), rest);
+    let ttl = Duration::from_secs(app.config().inline.cache_secs);
+    if let Some(results) = app.inline.get(&key, ttl) {
+        return results;
+    }
+
+    let parsed = match args::parse(plugin.params(), rest) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            let usage = format!("Usage: @{} {} {}", app.username, name, args::usage(plugin));
+            let usage = usage.trim_end().to_owned();
+            return vec![
+                InlineResult::new(e.to_string(), Outgoing::text(&usage)).description(usage)
+            ];
+        }
+    };
+
+    // Telegram sends a query for every keystroke, so only those that would
+    // reach the upstream API count against the user's limit.
+    let config = app.config();
+    let role = config.role_of(user);
+    let verdict = app
+        .limits
+        .check(&config.rate_limit, role, user, None, plugin.name());
+    if let Verdict::Limited { retry_after, .. } = verdict {
+        log::info!("Rate limited inline {} for {}", plugin.name(), user);
+        let text = format!(
+            "Slow down! Try again in {}s.",
+            retry_after.as_secs_f64().ceil().max(1.0)
+        );
+        return vec![InlineResult::new("Slow down!", Outgoing::text(&text)).description(text)];
+    }
+
+    log::info!("Answering inline {} for {}", plugin.name(), user);
+    app.reporter.command_ran(plugin.name());
+    match inline.answer(app.clone(), parsed).await {
+        Ok(results) => {
+            app.inline.insert(key, results.clone(), ttl);
+            results
+        }
+        Err(error) => {
+            let text = error.user_message(&format!("@{} {}", app.username, plugin.name()));
+            if error.is_failure() {
+                log::error!("Inline {} failed: {}", plugin.name(), error);
+            }
+            vec![InlineResult::new("Sorry!", Outgoing::text(&text)).description(text)]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfg::Config;
+    use crate::testing::{TestBot, USER_ID};
+
+    #[tokio::test]
+    async fn answers_only_configured_commands() {
+        let bot = TestBot::new();
+        let results = answer(&bot.app, USER_ID, "cat 418").await;
+        assert_eq!(
+            results,
+            [InlineResult::new(
+                "HTTP 418",
+                Outgoing::text("").photo_url("https://httpcats.com/418.jpg")
+            )]
+        );
+        assert!(answer(&bot.app, USER_ID, "ping").await.is_empty());
+        assert!(answer(&bot.app, USER_ID, "").await.is_empty());
+
+        let mut config = Config::default();
+        config.inline.plugins = vec!["aur".to_owned()];
+        let bot = TestBot::with_config(config);
+        assert!(answer(&bot.app, USER_ID, "cat 418").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn explains_bad_arguments() {
+        let bot = TestBot::new();
+        let results = answer(&bot.app, USER_ID, "cat 9000").await;
+        assert_eq!(results.len(), 1);
+        assert!(results[0]
+            .message
+            .text
+            .starts_with("Usage: @knight_bot cat"));
+    }
+
+    #[tokio::test]
+    async fn inline_queries_are_rate_limited() {
+        let mut config = Config::default();
+        config.rate_limit.user.burst = 1;
+        let bot = TestBot::with_config(config);
+
+        assert_eq!(
+            answer(&bot.app, USER_ID, "cat 418").await[0].title,
+            "HTTP 418"
+        );
+        // Cached results are free.
+        assert_eq!(
+            answer(&bot.app, USER_ID, "cat 418").await[0].title,
+            "HTTP 418"
+        );
+        let results = answer(&bot.app, USER_ID, "cat 404").await;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].title, "Slow down!");
+        assert!(results[0]
+            .message
+            .text
+            .starts_with("Slow down! Try again in"));
+    }
+
+    #[test]
+    fn cached_results_expire() {
+        let cache = InlineCache::default();
+        let results = vec![InlineResult::new("a", Outgoing::text("a"))];
+        cache.insert("q".to_owned(), results.clone(), Duration::from_secs(60));
+        assert_eq!(cache.get("q", Duration::from_secs(60)), Some(results));
+        assert_eq!(cache.get("q", Duration::ZERO), None);
+        assert_eq!(cache.get("other", Duration::from_secs(60)), None);
+    }
+}
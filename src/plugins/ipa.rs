@@ -4,86 +4,148 @@
 //! SPDX-License-Identifier: MIT
 //!
 
-// Description: Sends info about an IP Address.
+use crate::app::AppContext;
+use crate::plugins::{
+    args::{Kind, Param},
+    error::BotError,
+    html::Html,
+    req, BoxFuture, Category, Context, Plugin,
+};
+use crate::transport::{Incoming, Outgoing};
+use serde_json::Value;
+use std::net::IpAddr;
+use std::sync::Arc;
 
-use crate::plugins;
-use grammers_client::types::{InputMessage, Message};
+type Result = std::result::Result<(), BotError>;
 
-type Result = std::result::Result<(), Box<dyn std::error::Error>>;
+pub struct Ipa;
 
-pub async fn knightcmd_ipa(message: Message, addr: String) -> Result {
-    if addr.trim().is_empty() {
-        message
-            .reply(InputMessage::html("Send a <b>proper IP Address</b>!"))
+impl Plugin for Ipa {
+    fn name(&self) -> &'static str {
+        "ipa"
+    }
+
+    fn description(&self) -> &'static str {
+        "Sends info about an IP Address."
+    }
+
+    fn category(&self) -> Category {
+        Category::Network
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["1.1.1.1"]
+    }
+
+    fn params(&self) -> &'static [Param] {
+        const PARAMS: &[Param] = &[Param::optional("ip", Kind::Word)];
+        PARAMS
+    }
+
+    fn run(&self, ctx: Context) -> BoxFuture {
+        Box::pin(knightcmd_ipa(
+            ctx.app,
+            ctx.message,
+            ctx.parsed.get("ip").unwrap_or_default().to_owned(),
+        ))
+    }
+}
+
+pub async fn knightcmd_ipa(app: Arc<AppContext>, message: Incoming, addr: String) -> Result {
+    // Anything but an address would name another ipinfo.io endpoint.
+    if addr.parse::<IpAddr>().is_err() {
+        app.transport
+            .reply(&message, Outgoing::html("Send a <b>proper IP Address</b>!"))
             .await?;
         return Ok(());
     } else {
-        let msg = message
-            .reply(InputMessage::html(
-                "<b>Extracting info from ip addr........</b>",
-            ))
+        let msg = app
+            .transport
+            .reply(
+                &message,
+                Outgoing::html("<b>Extracting info from ip addr........</b>"),
+            )
             .await?;
-        let url = format!("https://ipinfo.io/{}", addr);
-        let response = plugins::req::make_request(url.to_string()).await;
-        if response.is_none() {
-            msg.edit("Something went wrong! Please try again").await?;
-            return Ok(());
-        } else if &response.clone().unwrap()["status"]
-            .to_string()
-            .trim_matches('"')
-            .to_string()
-            == "404"
-        {
-            msg.edit(InputMessage::html("Send a <b>proper IP Address</b>!"))
+        let url = req::with_path("https://ipinfo.io/", &[addr.as_str()]);
+        let response = match app.http.get_json::<Value>(&url).await {
+            Ok(response) => response,
+            Err(e) if e.is_not_found() => {
+                app.transport
+                    .edit(&msg, Outgoing::html("Send a <b>proper IP Address</b>!"))
+                    .await?;
+                return Ok(());
+            }
+            Err(e) => {
+                log::warn!("ipinfo lookup for {} failed: {:?}", addr, e);
+                app.transport
+                    .edit(
+                        &msg,
+                        format!("Something went wrong, {}! Please try again", e).into(),
+                    )
+                    .await?;
+                return Ok(());
+            }
+        };
+        if &response["status"].to_string().trim_matches('"').to_string() == "404" {
+            app.transport
+                .edit(&msg, Outgoing::html("Send a <b>proper IP Address</b>!"))
                 .await?;
             return Ok(());
         } else {
-            let hname = &response.clone().unwrap()["hostname"]
-                .to_string()
-                .trim_matches('"')
-                .to_string();
-            let city = &response.clone().unwrap()["city"]
-                .to_string()
-                .trim_matches('"')
-                .to_string();
-            let rgn = &response.clone().unwrap()["region"]
-                .to_string()
-                .trim_matches('"')
-                .to_string();
-            let ctry = &response.clone().unwrap()["country"]
-                .to_string()
-                .trim_matches('"')
-                .to_string();
-            let loc = &response.clone().unwrap()["loc"]
-                .to_string()
-                .trim_matches('"')
-                .to_string();
-            let org = &response.clone().unwrap()["org"]
+            let hname = &response["hostname"]
                 .to_string()
                 .trim_matches('"')
                 .to_string();
-            let postal = &response.clone().unwrap()["postal"]
+            let city = &response["city"].to_string().trim_matches('"').to_string();
+            let rgn = &response["region"].to_string().trim_matches('"').to_string();
+            let ctry = &response["country"]
                 .to_string()
                 .trim_matches('"')
                 .to_string();
-            let tz = &response.clone().unwrap()["timezone"]
+            let loc = &response["loc"].to_string().trim_matches('"').to_string();
+            let org = &response["org"].to_string().trim_matches('"').to_string();
+            let postal = &response["postal"].to_string().trim_matches('"').to_string();
+            let tz = &response["timezone"]
                 .to_string()
                 .trim_matches('"')
                 .to_string();
-            msg.edit(InputMessage::html(format!(
-                "<b>IP</b>: <code>{}</code>
-<b>Hostname</b>: {}
-<b>City</b>: {}
-<b>Region</b>: {}
-<b>Country</b>: {}
-<b>Lat/Long</b>: {}
-<b>Org</b>: {}
-<b>Postal</b>: {}
-<b>Timezone</b>: {}",
-                addr, hname, city, rgn, ctry, loc, org, postal, tz
-            )))
-            .await?;
+            let text = Html::new()
+                .bold("IP")
+                .text(": ")
+                .code(&addr)
+                .line()
+                .field("Hostname", hname)
+                .field("City", city)
+                .field("Region", rgn)
+                .field("Country", ctry)
+                .field("Lat/Long", loc)
+                .field("Org", org)
+                .field("Postal", postal)
+                .field("Timezone", tz);
+            app.transport
+                .edit(&msg, Outgoing::html(text.build().trim_end()))
+                .await?;
         }
     }
     return Ok(());
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, TestBot};
+
+    #[tokio::test]
+    async fn only_looks_up_addresses() {
+        let bot = TestBot::new();
+        for addr in ["", "../me", "1.1.1.1?token=x"] {
+            knightcmd_ipa(bot.app(), testing::message(1, "/ipa"), addr.to_owned())
+                .await
+                .unwrap();
+            assert_eq!(
+                bot.take_sent(),
+                Outgoing::html("Send a <b>proper IP Address</b>!").reply_to(Some(1))
+            );
+        }
+    }
+}
@@ -4,51 +4,92 @@
 //! SPDX-License-Identifier: MIT
 //!
 
-// Description: Extracts redirected URL from given link.
-
-use grammers_client::types::{InputMessage, Message};
+use crate::app::AppContext;
+use crate::plugins::{
+    args::{Kind, Param},
+    error::BotError,
+    BoxFuture, Category, Context, Plugin,
+};
+use crate::transport::{Incoming, Outgoing};
 use reqwest::header::LOCATION;
+use std::sync::Arc;
+
+type Result = std::result::Result<(), BotError>;
+
+pub struct Link;
+
+impl Plugin for Link {
+    fn name(&self) -> &'static str {
+        "link"
+    }
+
+    fn description(&self) -> &'static str {
+        "Extracts redirected URL from given link."
+    }
+
+    fn category(&self) -> Category {
+        Category::Network
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["https://example.com/redirect"]
+    }
+
+    fn params(&self) -> &'static [Param] {
+        const PARAMS: &[Param] = &[Param::optional("url", Kind::Url)];
+        PARAMS
+    }
 
-type Result = std::result::Result<(), Box<dyn std::error::Error>>;
+    fn run(&self, ctx: Context) -> BoxFuture {
+        Box::pin(knightcmd_link(
+            ctx.app,
+            ctx.message,
+            ctx.parsed.get("url").unwrap_or_default().to_owned(),
+        ))
+    }
+}
 
-pub async fn knightcmd_link(message: Message, url: String) -> Result {
+pub async fn knightcmd_link(app: Arc<AppContext>, message: Incoming, url: String) -> Result {
     if url.trim().is_empty() {
-        message
-            .reply(InputMessage::html("Send a <b>proper URL</b>!"))
-            .await?;
-        return Ok(());
-    } else if !url.starts_with("http://") && !url.starts_with("https://") {
-        message
-            .reply(InputMessage::html("<b>Invalid URL!</b>"))
+        app.transport
+            .reply(&message, Outgoing::html("Send a <b>proper URL</b>!"))
             .await?;
         return Ok(());
     } else {
-        let msg = message
-            .reply(InputMessage::html(
-                "<b>Extracting redirected URL from given link...</b>",
-            ))
+        let msg = app
+            .transport
+            .reply(
+                &message,
+                Outgoing::html("<b>Extracting redirected URL from given link...</b>"),
+            )
             .await?;
-        let req = reqwest::Client::new();
+        let req = app.http.client();
         let mut response = req.head(url).send().await?;
         while response.status().is_redirection() {
             if let Some(location) = response.headers().get(LOCATION) {
                 let location_str = location.to_str().unwrap_or_default();
                 response = req.head(location_str).send().await?;
             } else {
-                msg.edit(InputMessage::html(
-                    "<b>Error! Could not extract redirected URL!</b>",
-                ))
-                .await?;
+                app.transport
+                    .edit(
+                        &msg,
+                        Outgoing::html("<b>Error! Could not extract redirected URL!</b>"),
+                    )
+                    .await?;
                 return Ok(());
             }
         }
         if response.status().is_success() {
-            msg.edit(response.url().as_str()).await?
+            app.transport
+                .edit(&msg, response.url().as_str().into())
+                .await?
         } else {
-            msg.edit(InputMessage::html(
-                "<b>Error! Could not extract redirected URL!</b>",
-            ))
-            .await?;
+            app.transport
+                .edit(
+                    &msg,
+                    Outgoing::html("<b>Error! Could not extract redirected URL!</b>"),
+                )
+                .await?;
         }
     }
     return Ok(());
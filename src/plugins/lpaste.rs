@@ -4,26 +4,58 @@
 //! SPDX-License-Identifier: MIT
 //!
 
-// Description: Sends a shortlink of the replied link or the link given.
-
-use grammers_client::{
-    types::{InputMessage, Message},
-    Client,
+use crate::app::AppContext;
+use crate::plugins::{
+    args::{Kind, Param},
+    error::BotError,
+    html, paste, BoxFuture, Category, Context, Plugin,
 };
+use crate::transport::{Incoming, Outgoing};
 use librustbin::Client as RbinClient;
+use std::sync::Arc;
+
+type Result = std::result::Result<(), BotError>;
+
+pub struct Lpaste;
+
+impl Plugin for Lpaste {
+    fn name(&self) -> &'static str {
+        "lpaste"
+    }
+
+    fn description(&self) -> &'static str {
+        "Sends a shortlink of the replied link or the link given."
+    }
 
-type Result = std::result::Result<(), Box<dyn std::error::Error>>;
+    fn category(&self) -> Category {
+        Category::Network
+    }
+
+    fn params(&self) -> &'static [Param] {
+        const PARAMS: &[Param] = &[Param::optional("url", Kind::Word)];
+        PARAMS
+    }
+
+    fn run(&self, ctx: Context) -> BoxFuture {
+        Box::pin(knightcmd_lpaste(
+            ctx.app,
+            ctx.message,
+            ctx.parsed.get("url").unwrap_or_default().to_owned(),
+        ))
+    }
+}
 
 fn check_paste(url: &str) -> bool {
     !url.is_empty() && url != "This file is empty!" && url != "relative URL without a base"
 }
 
-pub async fn knightcmd_lpaste(client: Client, message: Message, link: String) -> Result {
-    let msg = message
-        .reply(InputMessage::html("<b>Pasting link...</b>"))
+pub async fn knightcmd_lpaste(app: Arc<AppContext>, message: Incoming, link: String) -> Result {
+    let msg = app
+        .transport
+        .reply(&message, Outgoing::html("<b>Pasting link...</b>"))
         .await?;
 
-    let text_to_paste = if let Some(reply) = client.get_reply_to_message(&message).await? {
+    let text_to_paste = if let Some(reply) = app.transport.reply_to_message(&message).await? {
         if !reply.text().is_empty() {
             Some(reply.text().to_string())
         } else {
@@ -36,24 +68,32 @@ pub async fn knightcmd_lpaste(client: Client, message: Message, link: String) ->
     };
 
     if let Some(text) = text_to_paste {
-        let rbin = RbinClient::new("https://bin.cyberknight777.dev".to_string());
+        let rbin = RbinClient::new(paste::RUSTBIN_URL.to_string());
 
         match rbin.paste_short(text) {
             Ok(url_raw) => {
                 let url = url_raw.trim().to_string();
                 if check_paste(&url) {
-                    msg.edit(InputMessage::html(format!("Link: {}", url)))
+                    app.transport
+                        .edit(
+                            &msg,
+                            Outgoing::html(format!("Link: {}", html::escape(&url))),
+                        )
                         .await?;
                 } else {
-                    msg.edit(InputMessage::html("<b>Paste failed!</b>")).await?;
+                    app.transport
+                        .edit(&msg, Outgoing::html("<b>Paste failed!</b>"))
+                        .await?;
                 }
             }
             Err(_) => {
-                msg.edit(InputMessage::html("<b>Paste failed!</b>")).await?;
+                app.transport
+                    .edit(&msg, Outgoing::html("<b>Paste failed!</b>"))
+                    .await?;
             }
         }
     } else {
-        msg.edit(InputMessage::html(
+        app.transport.edit(&msg, Outgoing::html(
             "Please reply to a <b>link</b> or reply with <b>/lpaste https://link.com</b> to shortlink it!",
         )).await?;
     }
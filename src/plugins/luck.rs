@@ -4,23 +4,40 @@
 //! SPDX-License-Identifier: MIT
 //!
 
-// Description: Says your lucky number.
+use crate::app::AppContext;
+use crate::plugins::{self, error::BotError, BoxFuture, Category, Context, Plugin};
+use crate::transport::{Incoming, Outgoing};
+use std::sync::Arc;
 
-use crate::plugins;
-use grammers_client::{
-    types::{InputMessage, Message},
-    Client,
-};
+type Result = std::result::Result<(), BotError>;
 
-type Result = std::result::Result<(), Box<dyn std::error::Error>>;
+pub struct Luck;
 
-pub async fn knightcmd_luck(client: Client, message: Message) -> Result {
+impl Plugin for Luck {
+    fn name(&self) -> &'static str {
+        "luck"
+    }
+
+    fn description(&self) -> &'static str {
+        "Says your lucky number."
+    }
+
+    fn category(&self) -> Category {
+        Category::Fun
+    }
+
+    fn run(&self, ctx: Context) -> BoxFuture {
+        Box::pin(knightcmd_luck(ctx.app, ctx.message))
+    }
+}
+
+pub async fn knightcmd_luck(app: Arc<AppContext>, message: Incoming) -> Result {
     let random_number = plugins::random(101); // modulo 101 to get a number between 0 to 100
     if let Some(id) = message.reply_to_message_id() {
-        client
-            .send_message(
+        app.transport
+            .send(
                 message.chat(),
-                InputMessage::html(format!(
+                Outgoing::html(format!(
                     "Your lucky number is: <code>{}</code>",
                     random_number
                 ))
@@ -28,11 +45,14 @@ pub async fn knightcmd_luck(client: Client, message: Message) -> Result {
             )
             .await?;
     } else {
-        message
-            .reply(InputMessage::html(format!(
-                "Your lucky number is: <code>{}</code>",
-                random_number
-            )))
+        app.transport
+            .reply(
+                &message,
+                Outgoing::html(format!(
+                    "Your lucky number is: <code>{}</code>",
+                    random_number
+                )),
+            )
             .await?;
     }
     return Ok(());
@@ -4,141 +4,113 @@
 //! SPDX-License-Identifier: MIT
 //!
 
-// Description: Gets the latest Magisk release according to the variant.
-
-use crate::plugins;
-use grammers_client::{
-    button, reply_markup,
-    types::{InputMessage, Message},
-    Client,
+use crate::app::AppContext;
+use crate::plugins::{
+    args::Args,
+    error::BotError,
+    inline::{Inline, InlineFuture, InlineResult},
+    req::ReqError,
+    BoxFuture, Category, Context, Plugin,
 };
+use crate::transport::{Button, Incoming, Outgoing};
+use serde_json::Value;
+use std::sync::Arc;
 
-type Result = std::result::Result<(), Box<dyn std::error::Error>>;
+type Result = std::result::Result<(), BotError>;
 
-pub async fn knightcmd_magisk(client: Client, message: Message) -> Result {
-    let stable =
-        format!("https://raw.githubusercontent.com/topjohnwu/magisk-files/master/stable.json");
-    let stable_resp = plugins::req::make_request(stable.to_string()).await;
+pub struct Magisk;
 
-    let beta = format!("https://raw.githubusercontent.com/topjohnwu/magisk-files/master/beta.json");
-    let beta_resp = plugins::req::make_request(beta.to_string()).await;
+impl Plugin for Magisk {
+    fn name(&self) -> &'static str {
+        "magisk"
+    }
 
-    let canary =
-        format!("https://raw.githubusercontent.com/topjohnwu/magisk-files/master/canary.json");
-    let canary_resp = plugins::req::make_request(canary.to_string()).await;
+    fn description(&self) -> &'static str {
+        "Gets the latest Magisk release according to the variant."
+    }
 
-    let stable_version;
-    let stable_link;
+    fn category(&self) -> Category {
+        Category::Android
+    }
 
-    let beta_version;
-    let beta_link;
+    fn inline(&self) -> Option<&dyn Inline> {
+        Some(self)
+    }
 
-    let canary_version;
-    let canary_link;
+    fn run(&self, ctx: Context) -> BoxFuture {
+        Box::pin(knightcmd_magisk(ctx.app, ctx.message))
+    }
+}
 
-    match stable_resp {
-        Some(stable_resp) => {
-            stable_link = (&stable_resp["magisk"]["link"]).to_string();
-            stable_version = (&stable_resp["magisk"]["version"]).to_string();
-        }
-        None => {
-            message
-                .reply(InputMessage::html(
-                    "Failed to get Magisk release information! (Stable)",
-                ))
-                .await?;
-            return Ok(());
-        }
+impl Inline for Magisk {
+    fn answer(&self, app: Arc<AppContext>, _args: Args) -> InlineFuture {
+        Box::pin(async move {
+            let releases = releases(&app).await.map_err(|(_, e)| e)?;
+            let versions = releases
+                .buttons
+                .iter()
+                .flatten()
+                .map(|button| button.text.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+            Ok(vec![
+                InlineResult::new("Latest Magisk Releases", releases).description(versions)
+            ])
+        })
     }
-    match beta_resp {
-        Some(beta_resp) => {
-            beta_link = (&beta_resp["magisk"]["link"]).to_string();
-            beta_version = (&beta_resp["magisk"]["version"]).to_string();
-        }
-        None => {
-            message
-                .reply(InputMessage::html(
-                    "Failed to get Magisk release information! (Beta)",
-                ))
-                .await?;
-            return Ok(());
-        }
+}
+
+const VARIANTS: [&str; 3] = ["Stable", "Beta", "Canary"];
+
+/// The latest release of every variant as download buttons, or the variant
+/// that could not be looked up.
+async fn releases(app: &AppContext) -> std::result::Result<Outgoing, (&'static str, ReqError)> {
+    let mut buttons = Vec::new();
+    for variant in VARIANTS {
+        let url = format!(
+            "https://raw.githubusercontent.com/topjohnwu/magisk-files/master/{}.json",
+            variant.to_lowercase()
+        );
+        let resp = match app.http.get_json::<Value>(&url).await {
+            Ok(resp) => resp,
+            Err(e) => {
+                log::warn!("Magisk {} lookup failed: {:?}", variant.to_lowercase(), e);
+                return Err((variant, e));
+            }
+        };
+        let version = (&resp["magisk"]["version"]).to_string();
+        let link = (&resp["magisk"]["link"]).to_string();
+        buttons.push(vec![Button::url(
+            format!("{}: {}", variant, version.trim_matches('"')),
+            link.trim_matches('"'),
+        )]);
     }
-    match canary_resp {
-        Some(canary_resp) => {
-            canary_link = (&canary_resp["magisk"]["link"]).to_string();
-            canary_version = (&canary_resp["magisk"]["version"]).to_string();
+    Ok(Outgoing::html("<b>Latest Magisk Releases</b>:").buttons(buttons))
+}
+
+pub async fn knightcmd_magisk(app: Arc<AppContext>, message: Incoming) -> Result {
+    match releases(&app).await {
+        Ok(releases) => {
+            if let Some(id) = message.reply_to_message_id() {
+                app.transport
+                    .send(message.chat(), releases.reply_to(Some(id)))
+                    .await?;
+            } else {
+                app.transport.reply(&message, releases).await?;
+            }
         }
-        None => {
-            message
-                .reply(InputMessage::html(
-                    "Failed to get Magisk release information! (Canary)",
-                ))
+        Err((variant, e)) => {
+            app.transport
+                .reply(
+                    &message,
+                    format!(
+                        "Failed to get Magisk release information! ({}: {})",
+                        variant, e
+                    )
+                    .into(),
+                )
                 .await?;
-            return Ok(());
         }
     }
-    if let Some(id) = message.reply_to_message_id() {
-        client
-            .send_message(
-                message.chat(),
-                InputMessage::html(format!("<b>Latest Magisk Releases</b>:"))
-                    .reply_to(Some(id))
-                    .reply_markup(&reply_markup::inline(vec![
-                        vec![button::url(
-                            format!(
-                                "Stable: {}",
-                                stable_version.to_string().trim_matches('"').to_string()
-                            ),
-                            stable_link.to_string().trim_matches('"').to_string(),
-                        )],
-                        vec![button::url(
-                            format!(
-                                "Beta: {}",
-                                beta_version.to_string().trim_matches('"').to_string()
-                            ),
-                            beta_link.to_string().trim_matches('"').to_string(),
-                        )],
-                        vec![button::url(
-                            format!(
-                                "Canary: {}",
-                                canary_version.to_string().trim_matches('"').to_string()
-                            ),
-                            canary_link.to_string().trim_matches('"').to_string(),
-                        )],
-                    ])),
-            )
-            .await?;
-    } else {
-        message
-            .reply(
-                InputMessage::html(format!("<b>Latest Magisk Releases</b>:")).reply_markup(
-                    &reply_markup::inline(vec![
-                        vec![button::url(
-                            format!(
-                                "Stable: {}",
-                                stable_version.to_string().trim_matches('"').to_string()
-                            ),
-                            stable_link.to_string().trim_matches('"').to_string(),
-                        )],
-                        vec![button::url(
-                            format!(
-                                "Beta: {}",
-                                beta_version.to_string().trim_matches('"').to_string()
-                            ),
-                            beta_link.to_string().trim_matches('"').to_string(),
-                        )],
-                        vec![button::url(
-                            format!(
-                                "Canary: {}",
-                                canary_version.to_string().trim_matches('"').to_string()
-                            ),
-                            canary_link.to_string().trim_matches('"').to_string(),
-                        )],
-                    ]),
-                ),
-            )
-            .await?;
-    }
     return Ok(());
 }
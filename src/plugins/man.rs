@@ -4,22 +4,59 @@
 //! SPDX-License-Identifier: MIT
 //!
 
-// Description: Gets information about a command from manpages.
-
-use grammers_client::{
-    types::{InputMessage, Message},
-    Client,
+use crate::app::AppContext;
+use crate::plugins::{
+    args::{Kind, Param},
+    error::BotError,
+    output::Output,
+    BoxFuture, Category, Context, Plugin,
 };
+use crate::transport::{Incoming, Outgoing};
 use std::process::Command;
+use std::sync::Arc;
+
+type Result = std::result::Result<(), BotError>;
 
-type Result = std::result::Result<(), Box<dyn std::error::Error>>;
+pub struct Man;
 
-pub async fn knightcmd_man(client: Client, message: Message, cmd: String) -> Result {
+impl Plugin for Man {
+    fn name(&self) -> &'static str {
+        "man"
+    }
+
+    fn description(&self) -> &'static str {
+        "Gets information about a command from manpages."
+    }
+
+    fn category(&self) -> Category {
+        Category::DevTools
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["tar"]
+    }
+
+    fn params(&self) -> &'static [Param] {
+        const PARAMS: &[Param] = &[Param::optional("command", Kind::Rest)];
+        PARAMS
+    }
+
+    fn run(&self, ctx: Context) -> BoxFuture {
+        Box::pin(knightcmd_man(
+            ctx.app,
+            ctx.message,
+            ctx.parsed.get("command").unwrap_or_default().to_owned(),
+        ))
+    }
+}
+
+pub async fn knightcmd_man(app: Arc<AppContext>, message: Incoming, cmd: String) -> Result {
     if cmd.trim().is_empty() {
-        message
-            .reply(InputMessage::html(
-                "<code>Provide a command to check its manual entry!</code>",
-            ))
+        app.transport
+            .reply(
+                &message,
+                Outgoing::html("<code>Provide a command to check its manual entry!</code>"),
+            )
             .await?;
         return Ok(());
     }
@@ -27,18 +64,14 @@ pub async fn knightcmd_man(client: Client, message: Message, cmd: String) -> Res
     let output_str = String::from_utf8_lossy(&output.stdout);
     let msg = output_str.trim();
     if msg.is_empty() {
-        message
-            .reply("No manual entry found for this command.")
+        app.transport
+            .reply(&message, "No manual entry found for this command.".into())
             .await?;
         return Ok(());
     }
-    let text = format!("<code>{}</code>", msg);
-    if let Some(id) = message.reply_to_message_id() {
-        client
-            .send_message(message.chat(), InputMessage::html(text).reply_to(Some(id)))
-            .await?;
-    } else {
-        message.reply(InputMessage::html(text)).await?;
-    }
+    Output::code(msg)
+        .reply_to(message.reply_to_message_id())
+        .reply(&app, &message)
+        .await?;
     return Ok(());
 }
@@ -4,14 +4,35 @@
 //! SPDX-License-Identifier: MIT
 //!
 
-use crate::cfg;
+use crate::app::AppContext;
+use crate::cfg::Role;
+use crate::limits::Verdict;
+use crate::menu;
+use crate::prefixes;
+use crate::transport::{telegram, Incoming, Outgoing, TransportError};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
 use std::sync::Arc;
 
+pub mod args;
+pub mod error;
+pub mod html;
+pub mod inline;
+mod output;
+pub mod req;
+
 mod anyone;
 mod aur;
+mod cache;
+mod cancel;
 mod cat;
+mod commands;
+mod disable;
+mod disabled;
 mod dog;
 mod eightball;
+mod enable;
 mod flipcoin;
 mod help;
 mod ipa;
@@ -25,7 +46,8 @@ mod neo;
 mod paste;
 mod ping;
 mod plant;
-mod req;
+mod prefix;
+mod reload;
 mod rtfm;
 mod run;
 mod sauce;
@@ -37,141 +59,352 @@ mod urb;
 mod whois;
 mod yaap;
 
+use args::{Args, Param};
+use error::BotError;
 use getrandom;
-use grammers_client::{types::Message, Client, Update};
-
-type Result = std::result::Result<(), Box<dyn std::error::Error>>;
-
-enum Command {
-    Anyone,
-    Aur(String),
-    Cat(i64),
-    Dog(i64),
-    EightBall,
-    FlipCoin,
-    Help,
-    Ipa(String),
-    Link(String),
-    Lpaste(String),
-    Luck,
-    Magisk,
-    Man(String),
-    Msg(String),
-    Neo,
-    Paste(String),
-    Ping,
-    Plant(i64),
-    Rtfm,
-    Run,
-    Sauce,
-    Sh(String),
-    Start,
-    Status,
-    Uid,
-    Urb(String),
-    Whois(String),
-    Yaap(String),
-}
-
-pub async fn handle_update(client: Client, update: Update) -> Result {
-    let config = Arc::new(cfg::Config::read().expect("cannot read the config"));
+use grammers_client::Update;
+use html::Html;
+use inline::Inline;
+
+type Result = std::result::Result<(), BotError>;
+
+/// Future returned by [`Plugin::run`].
+pub type BoxFuture = Pin<Box<dyn Future<Output = Result> + Send>>;
+
+/// Everything a command gets to work with when it is invoked.
+pub struct Context {
+    pub app: Arc<AppContext>,
+    pub message: Incoming,
+    /// Prefix the command was invoked with, e.g. `/`.
+    pub prefix: String,
+    /// Text following the command name, verbatim.
+    pub args: String,
+    /// `args` parsed according to [`Plugin::params`].
+    pub parsed: Args,
+}
+
+/// Section a command is listed under in `/help`, in listing order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Category {
+    General,
+    Fun,
+    DevTools,
+    Android,
+    Network,
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Category::General => "General",
+            Category::Fun => "Fun",
+            Category::DevTools => "Dev tools",
+            Category::Android => "Android",
+            Category::Network => "Network",
+        })
+    }
+}
+
+/// A single bot command.
+///
+/// Implement this in a file under `src/plugins/` and add it to the
+/// `plugins!` list below; dispatching, `/help` and permission checks are
+/// all derived from [`REGISTRY`].
+pub trait Plugin: Sync {
+    /// Name the command is invoked with, without any prefix.
+    fn name(&self) -> &'static str;
+
+    /// Other names the command also answers to.
+    fn aliases(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// One-line description shown in `/help`.
+    fn description(&self) -> &'static str;
+
+    /// Section of `/help` the command is listed under.
+    fn category(&self) -> Category {
+        Category::General
+    }
+
+    /// Example arguments shown by `/help <command>`, without the command.
+    fn examples(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Arguments the command takes; they are parsed and checked before
+    /// [`Plugin::run`] is called, and the usage text is derived from them.
+    fn params(&self) -> &'static [Param] {
+        const PARAMS: &[Param] = &[];
+        PARAMS
+    }
+
+    /// Least privileged role allowed to run the command.
+    ///
+    /// Commands requiring [`Role::Admin`] or above are invoked with the admin
+    /// prefix (`k.` by default) instead of the chat's command prefixes.
+    fn role(&self) -> Role {
+        Role::User
+    }
+
+    /// Answers inline queries (`@bot <name> <args>`) too, if enabled for
+    /// the command in the config.
+    fn inline(&self) -> Option<&dyn Inline> {
+        None
+    }
+
+    fn run(&self, ctx: Context) -> BoxFuture;
+}
+
+macro_rules! plugins {
+    ($($module:ident::$plugin:ident),* $(,)?) => {
+        /// Every command the bot knows about.
+        pub static REGISTRY: &[&dyn Plugin] = &[$(&$module::$plugin),*];
+    };
+}
+
+plugins! {
+    anyone::Anyone,
+    aur::Aur,
+    cache::Cache,
+    cancel::Cancel,
+    cat::Cat,
+    commands::Commands,
+    disable::Disable,
+    disabled::Disabled,
+    dog::Dog,
+    eightball::EightBall,
+    enable::Enable,
+    flipcoin::FlipCoin,
+    help::Help,
+    ipa::Ipa,
+    link::Link,
+    lpaste::Lpaste,
+    luck::Luck,
+    magisk::Magisk,
+    man::Man,
+    msg::Msg,
+    neo::Neo,
+    paste::Paste,
+    ping::Ping,
+    plant::Plant,
+    prefix::Prefix,
+    reload::Reload,
+    rtfm::Rtfm,
+    run::Run,
+    sauce::Sauce,
+    sh::Sh,
+    start::Start,
+    status::Status,
+    uid::Uid,
+    urb::Urb,
+    whois::Whois,
+    yaap::Yaap,
+}
+
+/// Whether the command lives under the admin prefix.
+pub fn is_admin_command(plugin: &dyn Plugin) -> bool {
+    plugin.role() >= Role::Admin
+}
+
+/// Looks up a command by name or alias within the public or admin namespace.
+pub fn find(name: &str, admin: bool) -> Option<&'static dyn Plugin> {
+    REGISTRY.iter().copied().find(|p| {
+        is_admin_command(*p) == admin && (p.name() == name || p.aliases().contains(&name))
+    })
+}
+
+pub async fn handle_update(app: Arc<AppContext>, update: Update) -> Result {
     match update {
-        Update::NewMessage(message)
-            if check_msg(&message) || check_cmd(&message, config.clone().admin_id) =>
-        {
-            log::info!("Responding to {}", message.chat().name());
-            handle_msg(client, message).await?
+        Update::NewMessage(message) if !message.outgoing() => {
+            handle_msg(app, telegram::incoming(&message)).await?
         }
+        Update::InlineQuery(query) => telegram::answer_inline(&app, query).await?,
         _ => {}
     }
 
     Ok(())
 }
 
-pub async fn handle_msg(client: Client, message: Message) -> Result {
-    let msg = message.text();
-    let _chat = message.chat(); // It is unused for the moment.
-    let cmd = msg.split_whitespace().next().unwrap_or("");
-    let args = msg.split_whitespace().skip(1).collect::<Vec<_>>();
-    let cmd = match cmd {
-        "/anyone" | "/anyone@ThekNIGHT_bot" => Command::Anyone,
-        "/aur" | "/aur@ThekNIGHT_bot" => Command::Aur(args.join(" ")),
-        "/cat" | "/cat@ThekNIGHT_bot" => Command::Cat(args.join(" ").parse().unwrap_or_default()),
-        "/dog" | "/dog@ThekNIGHT_bot" => Command::Dog(args.join(" ").parse().unwrap_or_default()),
-        "/eightball" | "/eightball@ThekNIGHT_bot" => Command::EightBall,
-        "/flipcoin" | "/flipcoin@ThekNIGHT_bot" => Command::FlipCoin,
-        "/help" | "/help@ThekNIGHT_bot" => Command::Help,
-        "/ipa" | "/ipa@ThekNIGHT_bot" => Command::Ipa(args.join(" ")),
-        "/link" | "/link@ThekNIGHT_bot" => Command::Link(args.join(" ")),
-        "/lpaste" | "/lpaste@ThekNIGHT_bot" => Command::Lpaste(args.join(" ")),
-        "/luck" | "/luck@ThekNIGHT_bot" => Command::Luck,
-        "/magisk" | "/magisk@ThekNIGHT_bot" => Command::Magisk,
-        "/man" | "/man@ThekNIGHT_bot" => Command::Man(args.join(" ")),
-        "/msg" | "/msg@ThekNIGHT_bot" => Command::Msg(args.join(" ")),
-        "/neo" | "/neo@ThekNIGHT_bot" => Command::Neo,
-        "/ping" | "/ping@ThekNIGHT_bot" => Command::Ping,
-        "/paste" | "/paste@ThekNIGHT_bot" => Command::Paste(args.join(" ")),
-        "/plant" | "/plant@ThekNIGHT_bot" => {
-            Command::Plant(args.join(" ").parse().unwrap_or_default())
+pub async fn handle_msg(app: Arc<AppContext>, message: Incoming) -> Result {
+    let msg = message.text().trim_start();
+    let (cmd, args) = match msg.find(char::is_whitespace) {
+        Some(at) => (&msg[..at], msg[at..].trim_start()),
+        None => (msg, ""),
+    };
+
+    let config = app.config();
+    let settings = app.store.chat(message.chat().id());
+    let chat_prefixes = settings
+        .prefixes
+        .as_deref()
+        .unwrap_or(&config.prefixes.commands);
+    let (mut prefix, name, mut admin) =
+        match prefixes::split(cmd, &config.prefixes.admin, chat_prefixes) {
+            Some(split) => split,
+            None => return Ok(()),
+        };
+    let mut name = match strip_mention(name, &app.username) {
+        Some(name) => name,
+        None => return Ok(()),
+    };
+    // Admin commands picked from Telegram's command menu.
+    if let Some(menu_name) = name.strip_prefix(menu::ADMIN_PREFIX) {
+        if !admin && find(menu_name, true).is_some() {
+            (prefix, name, admin) = (config.prefixes.admin.as_str(), menu_name, true);
+        }
+    }
+
+    let plugin = match find(name, admin) {
+        Some(plugin) => plugin,
+        None => return Ok(()),
+    };
+    if settings.disabled.contains(plugin.name()) {
+        log::debug!(
+            "Ignoring disabled {} in {}",
+            plugin.name(),
+            message.chat().name()
+        );
+        return Ok(());
+    }
+    let mut role = match message.sender() {
+        Some(sender) => config.role_of(sender.id()),
+        None => Role::User,
+    };
+    if role < Role::ChatAdmin && plugin.role() == Role::ChatAdmin {
+        match is_chat_admin(&app, &message).await {
+            Ok(true) => role = Role::ChatAdmin,
+            Ok(false) => {}
+            Err(e) => log::warn!(
+                "Failed to get permissions in {}: {}",
+                message.chat().name(),
+                e
+            ),
+        }
+    }
+    let command = format!("{}{}", prefix, plugin.name());
+    if role < plugin.role() {
+        log::info!(
+            "Denied {} to {} in {}",
+            plugin.name(),
+            role,
+            message.chat().name()
+        );
+        let error = BotError::PermissionDenied(plugin.role());
+        return report(&app, &message, &command, error).await;
+    }
+
+    let user = message.sender().map_or(0, |sender| sender.id());
+    let verdict = app.limits.check(
+        &config.rate_limit,
+        role,
+        user,
+        Some(message.chat().id()),
+        plugin.name(),
+    );
+    if let Verdict::Limited {
+        retry_after,
+        notify,
+    } = verdict
+    {
+        log::info!(
+            "Rate limited {} in {}",
+            plugin.name(),
+            message.chat().name()
+        );
+        if notify {
+            let text = format!(
+                "Slow down! Try again in {}s.",
+                retry_after.as_secs_f64().ceil().max(1.0)
+            );
+            app.transport.reply(&message, text.into()).await?;
+        }
+        return Ok(());
+    }
+
+    let parsed = match args::parse(plugin.params(), args) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            let usage = format!("Usage: {} {}", command, args::usage(plugin));
+            let error = BotError::bad_input(format!("{}\n{}", e, usage.trim_end()));
+            return report(&app, &message, &command, error).await;
         }
-        "/rtfm" | "/rtfm@ThekNIGHT_bot" => Command::Rtfm,
-        "/run" | "/run@ThekNIGHT_bot" => Command::Run,
-        "/sauce" | "/sauce@ThekNIGHT_bot" => Command::Sauce,
-        "/start" | "/start@ThekNIGHT_bot" => Command::Start,
-        "/status" | "/status@ThekNIGHT_bot" => Command::Status,
-        "/uid" | "/uid@ThekNIGHT_bot" => Command::Uid,
-        "/urb" | "/urb@ThekNIGHT_bot" => Command::Urb(args.join(" ")),
-        "/whois" | "/whois@ThekNIGHT_bot" => Command::Whois(args.join(" ")),
-        "/yaap" | "/yaap@ThekNIGHT_bot" => Command::Yaap(args.join(" ")),
-        "k.sh" => Command::Sh(args.join(" ").parse().unwrap_or_default()),
-        _ => return Ok(()),
     };
 
-    match cmd {
-        Command::Anyone => anyone::knightcmd_anyone(client, message).await?,
-        Command::Aur(pkg) => aur::knightcmd_aur(message, pkg).await?,
-        Command::Cat(kat) => cat::knightcmd_cat(client, message, kat).await?,
-        Command::Dog(doge) => dog::knightcmd_dog(client, message, doge).await?,
-        Command::EightBall => eightball::knightcmd_eightball(client, message).await?,
-        Command::FlipCoin => flipcoin::knightcmd_flipcoin(client, message).await?,
-        Command::Help => help::knightcmd_help(message).await?,
-        Command::Ipa(addr) => ipa::knightcmd_ipa(message, addr).await?,
-        Command::Link(url) => link::knightcmd_link(message, url).await?,
-        Command::Lpaste(link) => lpaste::knightcmd_lpaste(client, message, link).await?,
-        Command::Luck => luck::knightcmd_luck(client, message).await?,
-        Command::Magisk => magisk::knightcmd_magisk(client, message).await?,
-        Command::Man(cmd) => man::knightcmd_man(client, message, cmd).await?,
-        Command::Msg(text) => msg::knightcmd_msg(client, message, text).await?,
-        Command::Neo => neo::knightcmd_neo(message).await?,
-        Command::Ping => ping::knightcmd_ping(message).await?,
-        Command::Paste(past) => paste::knightcmd_paste(client, message, past).await?,
-        Command::Plant(plants) => plant::knightcmd_plant(client, message, plants).await?,
-        Command::Rtfm => rtfm::knightcmd_rtfm(client, message).await?,
-        Command::Run => run::knightcmd_run(message).await?,
-        Command::Sauce => sauce::knightcmd_sauce(client, message).await?,
-        Command::Sh(kcmd) => sh::knightcmd_sh(message, kcmd).await?,
-        Command::Start => start::knightcmd_start(message).await?,
-        Command::Status => status::knightcmd_status(message).await?,
-        Command::Uid => uid::knightcmd_uid(client, message).await?,
-        Command::Urb(word) => urb::knightcmd_urb(message, word).await?,
-        Command::Whois(site) => whois::knightcmd_whois(message, site).await?,
-        Command::Yaap(device) => yaap::knightcmd_yaap(client, message, device).await?,
+    log::info!("Responding to {}", message.chat().name());
+    app.reporter.command_ran(plugin.name());
+    let result = plugin
+        .run(Context {
+            app: app.clone(),
+            message: message.clone(),
+            prefix: prefix.to_owned(),
+            args: args.to_owned(),
+            parsed,
+        })
+        .await;
+    match result {
+        Ok(()) => Ok(()),
+        Err(error) => report(&app, &message, &command, error).await,
+    }
+}
+
+/// Tells the user why `command` failed and, if it was not their fault, posts
+/// the details to the log chat under an error ID they can be matched up by.
+async fn report(app: &AppContext, message: &Incoming, command: &str, error: BotError) -> Result {
+    if !error.is_failure() {
+        app.transport
+            .reply(message, error.user_message(command).into())
+            .await?;
+        return Ok(());
     }
 
+    let id = error_id();
+    log::error!(
+        "Error {} in {} ({}): {}",
+        id,
+        command,
+        message.chat().name(),
+        error
+    );
+    let text = Html::new()
+        .text(error.user_message(command))
+        .text(" ")
+        .italic(format!("(error {})", id));
+    app.transport
+        .reply(message, Outgoing::html(text.build()))
+        .await?;
+
+    app.reporter.error(app, &id, command, message, &error).await;
     Ok(())
 }
 
-fn check_msg(message: &Message) -> bool {
-    return !message.outgoing()
-        && message.text().starts_with('/')
-        && !message.text().starts_with("/ ")
-        || (message.text().ends_with("@ThekNIGHT_bot") && !message.text().starts_with("k.sh"));
+/// Whether the sender of `message` is an admin of the chat it was sent in.
+async fn is_chat_admin(
+    app: &AppContext,
+    message: &Incoming,
+) -> std::result::Result<bool, TransportError> {
+    match message.sender() {
+        Some(sender) => app.transport.is_chat_admin(message.chat(), sender).await,
+        None => Ok(false),
+    }
 }
 
-fn check_cmd(message: &Message, admin_id: i64) -> bool {
-    return !message.outgoing()
-        && message.text().starts_with("k.sh")
-        && (message.sender().unwrap().id() == admin_id);
+/// A short random ID to find an error in the logs by.
+fn error_id() -> String {
+    let mut buffer = [0; 4];
+    getrandom::fill(&mut buffer).expect("Failed to generate random number");
+    buffer.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Strips a trailing `@username` from a command if it addresses this bot.
+///
+/// Returns `None` when the command is addressed to some other bot.
+fn strip_mention<'a>(cmd: &'a str, username: &str) -> Option<&'a str> {
+    match cmd.split_once('@') {
+        Some((name, target)) if target.eq_ignore_ascii_case(username) => Some(name),
+        Some(_) => None,
+        None => Some(cmd),
+    }
 }
 
 pub fn random(modulo: u8) -> u8 {
@@ -179,3 +412,97 @@ pub fn random(modulo: u8) -> u8 {
     getrandom::fill(&mut buffer).expect("Failed to generate random number");
     return buffer[0] % modulo;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, Event, TestBot};
+
+    async fn send(bot: &TestBot, id: i32, text: &str) {
+        handle_msg(bot.app(), testing::message(id, text))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn ignores_other_prefixes_and_bots() {
+        let bot = TestBot::new();
+        send(&bot, 1, "!cat").await;
+        send(&bot, 2, "/cat@other_bot").await;
+        send(&bot, 3, "/nosuchcommand").await;
+        assert!(bot.take().is_empty());
+
+        send(&bot, 4, "/cat@knight_bot 200").await;
+        assert_eq!(
+            bot.take_sent(),
+            Outgoing::text("").photo_url("https://httpcats.com/200.jpg")
+        );
+    }
+
+    #[tokio::test]
+    async fn explains_bad_input_and_missing_roles() {
+        let bot = TestBot::new();
+        send(&bot, 1, "/cat 42").await;
+        assert_eq!(
+            bot.take_sent(),
+            Outgoing::text(
+                "<code> must be a number from 100 to 599, not `42`.\nUsage: /cat [code]"
+            )
+            .reply_to(Some(1))
+        );
+
+        send(&bot, 2, "k.sh ls").await;
+        assert_eq!(
+            bot.take_sent(),
+            Outgoing::text("Sorry, k.sh is only available to owners.").reply_to(Some(2))
+        );
+    }
+
+    #[tokio::test]
+    async fn chat_admins_disable_and_enable_commands() {
+        let bot = TestBot::new();
+        send(&bot, 1, "/disable cat").await;
+        assert_eq!(
+            bot.take_sent(),
+            Outgoing::text("Sorry, /disable is only available to chat admins.").reply_to(Some(1))
+        );
+
+        bot.transport.make_chat_admin(testing::USER_ID);
+        send(&bot, 2, "/disable cat").await;
+        assert!(matches!(bot.take().as_slice(), [Event::Sent { .. }]));
+        send(&bot, 3, "/cat").await;
+        assert!(bot.take().is_empty());
+
+        send(&bot, 4, "/enable cat").await;
+        bot.take();
+        send(&bot, 5, "/cat").await;
+        assert_eq!(
+            bot.take_sent(),
+            Outgoing::text("").photo_url("https://httpcats.com/404.jpg")
+        );
+    }
+
+    #[tokio::test]
+    async fn chat_prefixes_are_escaped_in_replies() {
+        let bot = TestBot::new();
+        bot.app
+            .store
+            .update_chat(testing::CHAT_ID, |chat| {
+                chat.prefixes = Some(vec!["&".to_owned()])
+            })
+            .await
+            .unwrap();
+        bot.transport.make_chat_admin(testing::USER_ID);
+
+        send(&bot, 1, "&disable cat").await;
+        assert_eq!(
+            bot.take_sent(),
+            Outgoing::html("<b>Disabled &amp;cat in this chat!</b>").reply_to(Some(1))
+        );
+        send(&bot, 2, "&disabled").await;
+        assert_eq!(
+            bot.take_sent(),
+            Outgoing::html("<b>Disabled in this chat:</b> &amp;cat").reply_to(Some(2))
+        );
+    }
+}
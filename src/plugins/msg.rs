@@ -4,35 +4,111 @@
 //! SPDX-License-Identifier: MIT
 //!
 
-// Description: Sends text.
-
-use grammers_client::{
-    types::{InputMessage, Message},
-    Client,
+use crate::app::AppContext;
+use crate::plugins::{
+    args::{Kind, Param},
+    error::BotError,
+    BoxFuture, Context, Plugin,
 };
+use crate::transport::{Incoming, Outgoing};
+use std::sync::Arc;
+
+type Result = std::result::Result<(), BotError>;
+
+pub struct Msg;
+
+impl Plugin for Msg {
+    fn name(&self) -> &'static str {
+        "msg"
+    }
+
+    fn description(&self) -> &'static str {
+        "Sends text."
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["Hello *there*"]
+    }
 
-type Result = std::result::Result<(), Box<dyn std::error::Error>>;
+    fn params(&self) -> &'static [Param] {
+        const PARAMS: &[Param] = &[Param::optional("text", Kind::Rest)];
+        PARAMS
+    }
 
-pub async fn knightcmd_msg(client: Client, message: Message, text: String) -> Result {
+    fn run(&self, ctx: Context) -> BoxFuture {
+        Box::pin(knightcmd_msg(
+            ctx.app,
+            ctx.message,
+            ctx.parsed.get("text").unwrap_or_default().to_owned(),
+        ))
+    }
+}
+
+pub async fn knightcmd_msg(app: Arc<AppContext>, message: Incoming, text: String) -> Result {
     if text.trim().is_empty() {
-        message
-            .reply(InputMessage::html(
-                "Send what? Give me <b>any text</b> to send!",
-            ))
+        app.transport
+            .reply(
+                &message,
+                Outgoing::html("Send what? Give me <b>any text</b> to send!"),
+            )
             .await?;
         return Ok(());
     }
     if let Some(id) = message.reply_to_message_id() {
-        client
-            .send_message(
+        app.transport
+            .send(
                 message.chat(),
-                InputMessage::markdown(text.trim().replace(r#"\n"#, "  \n")).reply_to(Some(id)),
+                Outgoing::markdown(text.trim().replace(r#"\n"#, "  \n")).reply_to(Some(id)),
             )
             .await?;
     } else {
-        message
-            .reply(InputMessage::markdown(text.trim().replace(r#"\n"#, "  \n")))
+        app.transport
+            .reply(
+                &message,
+                Outgoing::markdown(text.trim().replace(r#"\n"#, "  \n")),
+            )
             .await?;
     }
     return Ok(());
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, TestBot};
+
+    #[tokio::test]
+    async fn replies_with_markdown() {
+        let bot = TestBot::new();
+        let message = testing::message(1, "/msg Hello *there*\\nbye");
+        knightcmd_msg(bot.app(), message, "Hello *there*\\nbye".to_owned())
+            .await
+            .unwrap();
+        assert_eq!(
+            bot.take_sent(),
+            Outgoing::markdown("Hello *there*  \nbye").reply_to(Some(1))
+        );
+    }
+
+    #[tokio::test]
+    async fn answers_the_message_replied_to() {
+        let bot = TestBot::new();
+        let message = testing::message(2, "/msg hi").in_reply_to(Some(1));
+        knightcmd_msg(bot.app(), message, "hi".to_owned())
+            .await
+            .unwrap();
+        assert_eq!(bot.take_sent(), Outgoing::markdown("hi").reply_to(Some(1)));
+    }
+
+    #[tokio::test]
+    async fn asks_for_text() {
+        let bot = TestBot::new();
+        knightcmd_msg(bot.app(), testing::message(1, "/msg"), "  ".to_owned())
+            .await
+            .unwrap();
+        assert_eq!(
+            bot.take_sent(),
+            Outgoing::html("Send what? Give me <b>any text</b> to send!").reply_to(Some(1))
+        );
+    }
+}
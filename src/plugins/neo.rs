@@ -4,20 +4,40 @@
 //! SPDX-License-Identifier: MIT
 //!
 
-// Description: Sends neofetch output.
-
-use grammers_client::types::{InputMessage, Message};
+use crate::app::AppContext;
+use crate::plugins::{error::BotError, output::Output, BoxFuture, Category, Context, Plugin};
+use crate::transport::Incoming;
 use std::process::Command;
+use std::sync::Arc;
+
+type Result = std::result::Result<(), BotError>;
+
+pub struct Neo;
+
+impl Plugin for Neo {
+    fn name(&self) -> &'static str {
+        "neo"
+    }
 
-type Result = std::result::Result<(), Box<dyn std::error::Error>>;
+    fn description(&self) -> &'static str {
+        "Sends neofetch output."
+    }
+
+    fn category(&self) -> Category {
+        Category::DevTools
+    }
+
+    fn run(&self, ctx: Context) -> BoxFuture {
+        Box::pin(knightcmd_neo(ctx.app, ctx.message))
+    }
+}
 
-pub async fn knightcmd_neo(message: Message) -> Result {
+pub async fn knightcmd_neo(app: Arc<AppContext>, message: Incoming) -> Result {
     let neofetch = Command::new("neofetch")
         .arg("--stdout")
         .output()
         .expect("Failed to execute command!");
     let text = String::from_utf8_lossy(&neofetch.stdout).to_string();
-    let input_message = InputMessage::html(format!("<code>{}</code>", text.trim()));
-    message.reply(input_message).await?;
+    Output::code(text.trim()).reply(&app, &message).await?;
     return Ok(());
 }
@@ -0,0 +1,254 @@
+//!
+//! Copyright (C) 2023-2025 cyberknight777
+//!
+//! SPDX-License-Identifier: MIT
+//!
+
+use crate::app::AppContext;
+use crate::cfg::OutputMode;
+use crate::plugins::{error::BotError, html, paste};
+use crate::transport::{Incoming, Outgoing};
+
+type Result = std::result::Result<(), BotError>;
+
+/// Room left in every chunk for the `<code></code>` wrapping.
+const CODE_OVERHEAD: usize = 16;
+
+/// A reply that may be too long for a single Telegram message.
+///
+/// Depending on `[output]` in the config, long replies are split into several
+/// messages on line boundaries or uploaded as a `.txt` document.
+pub struct Output {
+    header: String,
+    body: String,
+    code: bool,
+    reply_to: Option<i32>,
+}
+
+impl Output {
+    /// Output shown in `<code>` blocks; `body` is raw text and gets escaped.
+    pub fn code(body: impl Into<String>) -> Self {
+        Output {
+            header: String::new(),
+            body: body.into(),
+            code: true,
+            reply_to: None,
+        }
+    }
+
+    /// Output that is already HTML, split between its lines.
+    pub fn html(body: impl Into<String>) -> Self {
+        Output {
+            code: false,
+            ..Output::code(body)
+        }
+    }
+
+    /// HTML shown above the body, in the first message only.
+    pub fn header(mut self, header: impl Into<String>) -> Self {
+        self.header = header.into();
+        self
+    }
+
+    /// Replies to another message than the command itself.
+    pub fn reply_to(mut self, id: Option<i32>) -> Self {
+        self.reply_to = id;
+        self
+    }
+
+    /// Sends the output as a reply to `message`.
+    pub async fn reply(self, app: &AppContext, message: &Incoming) -> Result {
+        self.send(app, message, None).await
+    }
+
+    /// Like [`Output::reply`], but the first part replaces the text of `msg`.
+    pub async fn edit(self, app: &AppContext, message: &Incoming, msg: &Incoming) -> Result {
+        self.send(app, message, Some(msg)).await
+    }
+
+    async fn send(self, app: &AppContext, message: &Incoming, edit: Option<&Incoming>) -> Result {
+        let config = app.config();
+        let limit = config.output.max_length;
+        let reply_to = self.reply_to.unwrap_or(message.id());
+
+        if self.header.len() + self.body.len() + CODE_OVERHEAD + 2 <= limit {
+            let text = self.join(&self.header, &self.body);
+            return deliver(app, message, edit, Outgoing::html(text), reply_to).await;
+        }
+
+        let chunks = split(&self.body, limit - CODE_OVERHEAD);
+        if config.output.mode == OutputMode::Split && chunks.len() <= config.output.max_messages {
+            let mut edit = edit;
+            let mut chunks = chunks.into_iter().peekable();
+            if !self.header.is_empty() {
+                let first = chunks.peek().map_or(0, |c| c.len());
+                if self.header.len() + first + CODE_OVERHEAD + 2 > limit {
+                    let text = Outgoing::html(self.header.clone());
+                    deliver(app, message, edit.take(), text, reply_to).await?;
+                } else if let Some(chunk) = chunks.next() {
+                    let text = Outgoing::html(self.join(&self.header, &chunk));
+                    deliver(app, message, edit.take(), text, reply_to).await?;
+                }
+            }
+            for chunk in chunks {
+                let text = Outgoing::html(self.join("", &chunk));
+                deliver(app, message, edit.take(), text, reply_to).await?;
+            }
+            return Ok(());
+        }
+
+        let plain = if self.code {
+            self.body.clone()
+        } else {
+            html::to_plain(&self.body)
+        };
+        let mut caption = if self.header.is_empty() {
+            "<b>Output too long, sent as a file.</b>".to_owned()
+        } else {
+            self.header.clone()
+        };
+        if config.output.paste {
+            match paste::paste(plain.clone()).await {
+                Some(url) => caption.push_str(&format!("\nPaste: {}", url)),
+                None => log::warn!("Failed to paste long output"),
+            }
+        }
+
+        if let Some(msg) = edit {
+            app.transport
+                .edit(msg, Outgoing::html(caption.clone()))
+                .await?;
+        }
+        let document = Outgoing::html(caption).document("output.txt", plain.into_bytes());
+        deliver(app, message, None, document, reply_to).await
+    }
+
+    fn join(&self, header: &str, chunk: &str) -> String {
+        let body = if self.code {
+            format!("<code>{}</code>", html::escape(chunk.trim_end()))
+        } else {
+            chunk.trim_end().to_owned()
+        };
+        if header.is_empty() {
+            body
+        } else {
+            format!("{}\n\n{}", header, body)
+        }
+    }
+}
+
+/// Edits `edit` if given, otherwise sends a new message replying to `reply_to`.
+async fn deliver(
+    app: &AppContext,
+    message: &Incoming,
+    edit: Option<&Incoming>,
+    text: Outgoing,
+    reply_to: i32,
+) -> Result {
+    match edit {
+        Some(msg) => app.transport.edit(msg, text).await?,
+        None => {
+            app.transport
+                .send(message.chat(), text.reply_to(Some(reply_to)))
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Splits `text` into chunks of at most `limit` bytes, preferring line boundaries.
+fn split(text: &str, limit: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+
+    for line in text.lines() {
+        let mut line = line;
+        while line.len() > limit {
+            let mut at = limit;
+            while !line.is_char_boundary(at) {
+                at -= 1;
+            }
+            if !current.is_empty() {
+                chunks.push(std::mem::take(&mut current));
+            }
+            chunks.push(line[..at].to_owned());
+            line = &line[at..];
+        }
+        if !current.is_empty() && current.len() + line.len() + 1 > limit {
+            chunks.push(std::mem::take(&mut current));
+        }
+        current.push_str(line);
+        current.push('\n');
+    }
+    if !current.trim().is_empty() {
+        chunks.push(current);
+    }
+
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfg::Config;
+    use crate::testing::{self, Event, FakeTransport};
+
+    #[test]
+    fn code_output_is_escaped() {
+        let output = Output::code("<b>&</b>").header("<b>exit status: 0</b>");
+        assert_eq!(
+            output.join(&output.header, &output.body),
+            "<b>exit status: 0</b>\n\n<code>&lt;b&gt;&amp;&lt;/b&gt;</code>"
+        );
+    }
+
+    #[test]
+    fn split_keeps_lines_whole() {
+        let text = "aaaa\nbbbb\ncccc\n";
+        assert_eq!(split(text, 10), vec!["aaaa\nbbbb\n", "cccc\n"]);
+    }
+
+    #[test]
+    fn split_breaks_long_lines_on_char_boundaries() {
+        let chunks = split("ééééé", 4);
+        assert_eq!(chunks, vec!["éé", "éé", "é\n"]);
+    }
+
+    #[tokio::test]
+    async fn long_output_becomes_a_document() {
+        let transport = FakeTransport::new();
+        let mut config = Config::default();
+        config.output.max_length = 100;
+        config.output.mode = OutputMode::Document;
+        let app = testing::app_with(transport.clone(), config);
+        let message = testing::message(1, "k.sh yes");
+        let msg = app
+            .transport
+            .reply(&message, "Running...".into())
+            .await
+            .unwrap();
+        transport.take();
+
+        let body = "y\n".repeat(100);
+        Output::code(body.clone())
+            .edit(&app, &message, &msg)
+            .await
+            .unwrap();
+        let caption = "<b>Output too long, sent as a file.</b>";
+        assert_eq!(
+            transport.take(),
+            [
+                Event::Edited {
+                    id: msg.id(),
+                    outgoing: Outgoing::html(caption),
+                },
+                Event::Sent {
+                    chat: testing::CHAT_ID,
+                    outgoing: Outgoing::html(caption)
+                        .document("output.txt", body.into_bytes())
+                        .reply_to(Some(1)),
+                },
+            ]
+        );
+    }
+}
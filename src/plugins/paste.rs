@@ -4,85 +4,135 @@
 //! SPDX-License-Identifier: MIT
 //!
 
-// Description: Sends a pastebin link of the replied message (or document) or the text given.
-
-use grammers_client::{
-    types::{InputMessage, Media, Message},
-    Client,
+use crate::app::AppContext;
+use crate::plugins::{
+    args::{Kind, Param},
+    error::BotError,
+    html, BoxFuture, Category, Context, Plugin,
 };
+use crate::transport::{Incoming, Outgoing};
 use librustbin::Client as RbinClient;
-use std::fs;
-use tokio::fs as tokio_fs;
-use tokio::io::AsyncReadExt;
+use std::sync::Arc;
+use tokio::task;
+
+type Result = std::result::Result<(), BotError>;
+
+pub struct Paste;
+
+impl Plugin for Paste {
+    fn name(&self) -> &'static str {
+        "paste"
+    }
+
+    fn description(&self) -> &'static str {
+        "Sends a pastebin link of the replied message (or document) or the text given."
+    }
+
+    fn category(&self) -> Category {
+        Category::DevTools
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["fn main() {}"]
+    }
+
+    fn params(&self) -> &'static [Param] {
+        const PARAMS: &[Param] = &[Param::optional("text", Kind::Rest)];
+        PARAMS
+    }
+
+    fn run(&self, ctx: Context) -> BoxFuture {
+        Box::pin(knightcmd_paste(
+            ctx.app,
+            ctx.message,
+            ctx.parsed.get("text").unwrap_or_default().to_owned(),
+        ))
+    }
+}
 
-type Result = std::result::Result<(), Box<dyn std::error::Error>>;
+/// Rustbin instance pastes are sent to.
+pub const RUSTBIN_URL: &str = "https://bin.cyberknight777.dev";
 
 fn check_paste(url: &str) -> bool {
     !url.is_empty() && url != "This file is empty!" && url != "This file exceeds the file limit"
 }
 
-async fn fail_edit(msg: &Message) -> Result {
-    msg.edit(InputMessage::html("<b>Paste failed!</b>")).await?;
-    return Ok(());
+/// Pastes `content` with syntax highlighting, returning its URL.
+///
+/// The rustbin client blocks, so it runs off the runtime thread.
+pub async fn paste(content: String) -> Option<String> {
+    let url = task::spawn_blocking(move || {
+        let rbin = RbinClient::new(RUSTBIN_URL.to_string());
+        rbin.paste_highlight(content).ok()
+    })
+    .await
+    .ok()
+    .flatten()?;
+    let url = url.trim().to_string();
+    if check_paste(&url) {
+        Some(url)
+    } else {
+        None
+    }
 }
 
-async fn paste_edit(msg: &Message, content: String) -> Result {
-    let rbin = RbinClient::new("https://bin.cyberknight777.dev".to_string());
+async fn fail_edit(app: &AppContext, msg: &Incoming) -> Result {
+    app.transport
+        .edit(&msg, Outgoing::html("<b>Paste failed!</b>"))
+        .await?;
+    return Ok(());
+}
 
-    match rbin.paste_highlight(content) {
-        Ok(url_raw) => {
-            let url = url_raw.trim().to_string();
-            if check_paste(&url) {
-                msg.edit(InputMessage::html(format!("Link: {}", url)))
-                    .await?;
-            } else {
-                fail_edit(&msg).await?;
-            }
+async fn paste_edit(app: &AppContext, msg: &Incoming, content: String) -> Result {
+    match paste(content).await {
+        Some(url) => {
+            app.transport
+                .edit(
+                    &msg,
+                    Outgoing::html(format!("Link: {}", html::escape(&url))),
+                )
+                .await?;
         }
-        Err(_) => {
-            fail_edit(&msg).await?;
+        None => {
+            fail_edit(app, &msg).await?;
         }
     }
     return Ok(());
 }
 
-pub async fn knightcmd_paste(client: Client, message: Message, past: String) -> Result {
+pub async fn knightcmd_paste(app: Arc<AppContext>, message: Incoming, past: String) -> Result {
     const MAX_SIZE: i64 = 5 * 1024 * 1024;
 
-    let msg = message
-        .reply(InputMessage::html("<b>Pasting content...</b>"))
+    let msg = app
+        .transport
+        .reply(&message, Outgoing::html("<b>Pasting content...</b>"))
         .await?;
 
-    if let Some(reply) = client.get_reply_to_message(&message).await? {
-        if let Some(ref media @ Media::Document(ref doc)) = reply.media() {
-            if doc.size() > MAX_SIZE {
-                msg.edit(InputMessage::html("<b>File too large (max 5MB)</b>"))
+    if let Some(reply) = app.transport.reply_to_message(&message).await? {
+        if let Some(doc) = reply.document() {
+            if doc.size > MAX_SIZE {
+                app.transport
+                    .edit(&msg, Outgoing::html("<b>File too large (max 5MB)</b>"))
                     .await?;
                 return Ok(());
             }
 
-            let file_path = format!("/tmp/telegram_paste_{}", reply.id());
-
-            client.download_media(&media, file_path.clone()).await?;
-
-            let mut file = tokio_fs::File::open(&file_path).await?;
-            let mut bytes = Vec::new();
-            file.read_to_end(&mut bytes).await?;
-
-            let contents = String::from_utf8_lossy(&bytes).to_string();
-
-            paste_edit(&msg, contents).await?;
-
-            let _ = fs::remove_file(&file_path);
+            match app.transport.download_media(&reply).await? {
+                Some(bytes) => {
+                    let contents = String::from_utf8_lossy(&bytes).to_string();
+                    paste_edit(&app, &msg, contents).await?;
+                }
+                None => fail_edit(&app, &msg).await?,
+            }
         } else if !reply.text().is_empty() {
-            paste_edit(&msg, reply.text().to_string()).await?;
+            paste_edit(&app, &msg, reply.text().to_string()).await?;
         } else {
-            fail_edit(&msg).await?;
+            fail_edit(&app, &msg).await?;
         }
     } else if !past.is_empty() {
-        paste_edit(&msg, past).await?;
+        paste_edit(&app, &msg, past).await?;
     } else {
-        msg.edit(InputMessage::html(
+        app.transport.edit(&msg, Outgoing::html(
             "Please reply to a <b>message</b> or reply with <b>/paste yourtext</b> to paste it!",
         ))
         .await?;
@@ -90,3 +140,39 @@ pub async fn knightcmd_paste(client: Client, message: Message, past: String) ->
 
     return Ok(());
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, Event, TestBot};
+    use crate::transport::Document;
+
+    #[tokio::test]
+    async fn refuses_large_documents() {
+        let bot = TestBot::new();
+        let document = Document {
+            name: "huge.log".to_owned(),
+            size: 6 * 1024 * 1024,
+        };
+        let replied = testing::message(1, "").with_document(document);
+        bot.transport.remember(replied);
+        bot.transport.attach(1, "too much");
+        let message = testing::message(2, "/paste").in_reply_to(Some(1));
+        knightcmd_paste(bot.app(), message, String::new())
+            .await
+            .unwrap();
+        assert_eq!(
+            bot.take(),
+            [
+                Event::Sent {
+                    chat: testing::CHAT_ID,
+                    outgoing: Outgoing::html("<b>Pasting content...</b>").reply_to(Some(2)),
+                },
+                Event::Edited {
+                    id: 1000,
+                    outgoing: Outgoing::html("<b>File too large (max 5MB)</b>"),
+                },
+            ]
+        );
+    }
+}
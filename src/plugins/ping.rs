@@ -4,18 +4,40 @@
 //! SPDX-License-Identifier: MIT
 //!
 
-// Description: Checks how fast I can respond.
-
-use grammers_client::types::Message;
+use crate::app::AppContext;
+use crate::plugins::{error::BotError, BoxFuture, Context, Plugin};
+use crate::transport::Incoming;
+use std::sync::Arc;
 use std::time::SystemTime;
 
-type Result = std::result::Result<(), Box<dyn std::error::Error>>;
+type Result = std::result::Result<(), BotError>;
+
+pub struct Ping;
+
+impl Plugin for Ping {
+    fn name(&self) -> &'static str {
+        "ping"
+    }
+
+    fn description(&self) -> &'static str {
+        "Checks how fast I can respond."
+    }
+
+    fn run(&self, ctx: Context) -> BoxFuture {
+        Box::pin(knightcmd_ping(ctx.app, ctx.message))
+    }
+}
 
-pub async fn knightcmd_ping(message: Message) -> Result {
+pub async fn knightcmd_ping(app: Arc<AppContext>, message: Incoming) -> Result {
     let start = SystemTime::now();
-    let msg = message.reply("Pinging........").await?;
+    let msg = app
+        .transport
+        .reply(&message, "Pinging........".into())
+        .await?;
     let end = SystemTime::now();
     let ping = end.duration_since(start).unwrap().as_millis();
-    msg.edit(format!("Pong! {}ms", ping)).await?;
+    app.transport
+        .edit(&msg, format!("Pong! {}ms", ping).into())
+        .await?;
     return Ok(());
 }
@@ -0,0 +1,126 @@
+//!
+//! Copyright (C) 2023-2025 cyberknight777
+//!
+//! SPDX-License-Identifier: MIT
+//!
+
+use crate::app::AppContext;
+use crate::cfg::Role;
+use crate::plugins::{
+    args::{Args, Kind, Param},
+    error::BotError,
+    html::Html,
+    BoxFuture, Context, Plugin,
+};
+use crate::prefixes;
+use crate::transport::{Incoming, Outgoing};
+use std::sync::Arc;
+
+type Result = std::result::Result<(), BotError>;
+
+pub struct Prefix;
+
+impl Plugin for Prefix {
+    fn name(&self) -> &'static str {
+        "prefix"
+    }
+
+    fn description(&self) -> &'static str {
+        "Shows the command prefixes of this chat, or sets or resets them."
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["set / !", "reset"]
+    }
+
+    fn params(&self) -> &'static [Param] {
+        const PARAMS: &[Param] = &[
+            Param::optional("action", Kind::Word),
+            Param::optional("prefixes", Kind::Rest),
+        ];
+        PARAMS
+    }
+
+    fn role(&self) -> Role {
+        Role::Admin
+    }
+
+    fn run(&self, ctx: Context) -> BoxFuture {
+        Box::pin(knightcmd_prefix(
+            ctx.app,
+            ctx.message,
+            ctx.prefix,
+            ctx.parsed,
+        ))
+    }
+}
+
+pub async fn knightcmd_prefix(
+    app: Arc<AppContext>,
+    message: Incoming,
+    prefix: String,
+    args: Args,
+) -> Result {
+    let chat = message.chat().id();
+    let text = match args.get("action") {
+        None => {
+            let config = app.config();
+            let (prefixes, source) = match app.store.chat(chat).prefixes {
+                Some(prefixes) => (prefixes, "set for this chat"),
+                None => (config.prefixes.commands.clone(), "from the config"),
+            };
+            Html::new()
+                .field("Prefixes", format!("{} ({})", prefixes.join(" "), source))
+                .field("Admin prefix", &config.prefixes.admin)
+        }
+        Some("set") => {
+            let prefixes = args
+                .get("prefixes")
+                .unwrap_or_default()
+                .split_whitespace()
+                .map(str::to_owned)
+                .collect::<Vec<_>>();
+            if prefixes.is_empty() {
+                return Err(BotError::bad_input(format!(
+                    "Give me the prefixes to use, e.g. {}prefix set / !",
+                    prefix
+                )));
+            }
+            let admin = app.config().prefixes.admin.clone();
+            for new in &prefixes {
+                prefixes::check(new).map_err(BotError::bad_input)?;
+                if new.starts_with(&admin) {
+                    return Err(BotError::bad_input(format!(
+                        "`{}` would be taken for the admin prefix `{}`.",
+                        new, admin
+                    )));
+                }
+            }
+            app.store
+                .update_chat(chat, |settings| settings.prefixes = Some(prefixes.clone()))
+                .await?;
+            Html::new().bold(format!("Prefixes set to {}", prefixes.join(" ")))
+        }
+        Some("reset") => {
+            if app
+                .store
+                .update_chat(chat, |settings| settings.prefixes.take().is_some())
+                .await?
+            {
+                Html::new().bold("Back to the configured prefixes!")
+            } else {
+                Html::new().bold("This chat already uses the configured prefixes!")
+            }
+        }
+        Some(action) => {
+            return Err(BotError::bad_input(format!(
+                "Unknown action `{}`, try {}prefix set or {}prefix reset.",
+                action, prefix, prefix
+            )));
+        }
+    };
+    app.transport
+        .reply(&message, Outgoing::html(text.build()))
+        .await?;
+    return Ok(());
+}
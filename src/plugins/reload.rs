@@ -0,0 +1,91 @@
+//!
+//! Copyright (C) 2023-2025 cyberknight777
+//!
+//! SPDX-License-Identifier: MIT
+//!
+
+use crate::app::AppContext;
+use crate::cfg::{Config, Role};
+use crate::plugins::{error::BotError, html, BoxFuture, Context, Plugin};
+use crate::transport::{Incoming, Outgoing};
+use std::sync::Arc;
+
+type Result = std::result::Result<(), BotError>;
+
+pub struct Reload;
+
+impl Plugin for Reload {
+    fn name(&self) -> &'static str {
+        "reload"
+    }
+
+    fn description(&self) -> &'static str {
+        "Re-reads config.toml without restarting."
+    }
+
+    fn role(&self) -> Role {
+        Role::Admin
+    }
+
+    fn run(&self, ctx: Context) -> BoxFuture {
+        Box::pin(knightcmd_reload(ctx.app, ctx.message))
+    }
+}
+
+pub async fn knightcmd_reload(app: Arc<AppContext>, message: Incoming) -> Result {
+    let text = match app.reload() {
+        Ok(old) => {
+            let new = app.config();
+            if needs_restart(&old, &new) {
+                "<b>Config reloaded!</b>\nTelegram credentials, session file, HTTP, outbox, handler, connection or store settings changed, restart me for them to take effect.".to_owned()
+            } else {
+                "<b>Config reloaded!</b>".to_owned()
+            }
+        }
+        Err(e) => {
+            log::error!("Failed to reload config: {}", e);
+            format!(
+                "<b>Failed to reload config, keeping the old one:</b>\n<code>{}</code>",
+                html::escape(&e.to_string())
+            )
+        }
+    };
+    app.transport.reply(&message, Outgoing::html(text)).await?;
+    return Ok(());
+}
+
+/// Whether `new` changes settings that are only read on startup.
+fn needs_restart(old: &Config, new: &Config) -> bool {
+    old.api_id != new.api_id
+        || old.api_hash != new.api_hash
+        || old.bot_token != new.bot_token
+        || old.session_file != new.session_file
+        || old.http != new.http
+        || old.outbox != new.outbox
+        || old.handlers != new.handlers
+        || old.connection != new.connection
+        || old.store.path != new.store.path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn startup_settings_need_a_restart() {
+        let old = Config::default();
+        assert!(!needs_restart(&old, &Config::default()));
+
+        let mut new = Config::default();
+        new.log_chat_id = Some(-1001234567890);
+        assert!(!needs_restart(&old, &new));
+
+        let mut new = Config::default();
+        new.handlers.max_concurrent = 4;
+        assert!(needs_restart(&old, &new));
+
+        let mut new = Config::default();
+        new.connection.watchdog_secs = 60;
+        assert!(needs_restart(&old, &new));
+    }
+}
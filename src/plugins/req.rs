@@ -4,20 +4,667 @@
 //! SPDX-License-Identifier: MIT
 //!
 
-use reqwest;
-use serde_json::Value;
-
-pub async fn make_request(url: String) -> Option<Value> {
-    let response = reqwest::get(&url).await.ok()?.text().await.ok();
-
-    let response = match response {
-        Some(val) => val.trim().to_string(),
-        None => return None,
-    };
-    let response: Value = match serde_json::from_str(&response) {
-        Ok(val) => val,
-        _ => return None,
-    };
-
-    Some(response)
+use crate::cfg::{CacheConfig, HttpConfig};
+use reqwest::{
+    header::{HeaderMap, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    Response, StatusCode,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use std::{fmt, fs, io};
+use tokio::task;
+
+/// Why a request to an upstream API failed.
+#[derive(Debug)]
+pub enum ReqError {
+    /// The server could not be reached or took too long to answer.
+    Network(reqwest::Error),
+    /// The server answered with a non-success status.
+    Status(StatusCode),
+    /// The response body was not what we expected.
+    Decode(serde_json::Error),
+}
+
+impl ReqError {
+    /// Whether the server said the requested thing does not exist.
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, ReqError::Status(StatusCode::NOT_FOUND))
+    }
+}
+
+impl fmt::Display for ReqError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReqError::Network(e) if e.is_timeout() => f.write_str("the request timed out"),
+            ReqError::Network(_) => f.write_str("couldn't reach the server"),
+            ReqError::Status(status) => write!(f, "the server answered {}", status),
+            ReqError::Decode(_) => f.write_str("the server sent an unexpected response"),
+        }
+    }
+}
+
+impl std::error::Error for ReqError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReqError::Network(e) => Some(e),
+            ReqError::Status(_) => None,
+            ReqError::Decode(e) => Some(e),
+        }
+    }
+}
+
+/// HTTP client shared by all plugins, configured from `[http]`.
+///
+/// Text and JSON lookups go through a [`ResponseCache`].
+pub struct Http {
+    client: reqwest::Client,
+    retries: u32,
+    backoff: Duration,
+    cache: ResponseCache,
+    /// URL prefixes replaced before sending, so tests can point plugins at a
+    /// local server
+    #[cfg(test)]
+    redirects: Vec<(String, String)>,
+}
+
+impl Http {
+    pub fn new(config: &HttpConfig) -> Result<Self, reqwest::Error> {
+        let mut builder = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .user_agent(&config.user_agent);
+        if let Some(proxy) = &config.proxy {
+            builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+        }
+        Ok(Http {
+            client: builder.build()?,
+            retries: config.retries,
+            backoff: Duration::from_millis(config.backoff_ms),
+            cache: ResponseCache::new(&config.cache),
+            #[cfg(test)]
+            redirects: Vec::new(),
+        })
+    }
+
+    /// Sends requests for URLs starting with `from` to `to` instead.
+    #[cfg(test)]
+    pub fn redirect(&mut self, from: impl Into<String>, to: impl Into<String>) {
+        self.redirects.push((from.into(), to.into()));
+    }
+
+    #[cfg(test)]
+    fn redirected(&self, url: &str) -> String {
+        match self
+            .redirects
+            .iter()
+            .find(|(from, _)| url.starts_with(from.as_str()))
+        {
+            Some((from, to)) => format!("{}{}", to, &url[from.len()..]),
+            None => url.to_owned(),
+        }
+    }
+
+    /// The underlying client, for requests other than plain GETs.
+    pub fn client(&self) -> &reqwest::Client {
+        &self.client
+    }
+
+    pub fn cache(&self) -> &ResponseCache {
+        &self.cache
+    }
+
+    /// GETs `url`, retrying with exponential backoff on network errors, 5xx and 429.
+    pub async fn get(&self, url: &str) -> Result<Response, ReqError> {
+        self.send(url, None).await
+    }
+
+    /// Like [`Http::get`], but asks the server to answer `304` if `validators` still match.
+    async fn send(&self, url: &str, validators: Option<&Validators>) -> Result<Response, ReqError> {
+        #[cfg(test)]
+        let url = self.redirected(url);
+        #[cfg(test)]
+        let url = url.as_str();
+        let mut attempt = 0;
+        loop {
+            let mut request = self.client.get(url);
+            if let Some(validators) = validators {
+                if let Some(etag) = &validators.etag {
+                    request = request.header(IF_NONE_MATCH, etag);
+                }
+                if let Some(last_modified) = &validators.last_modified {
+                    request = request.header(IF_MODIFIED_SINCE, last_modified);
+                }
+            }
+            let result = request.send().await;
+            let retry = match &result {
+                Ok(response) => {
+                    response.status().is_server_error()
+                        || response.status() == StatusCode::TOO_MANY_REQUESTS
+                }
+                Err(e) => e.is_timeout() || e.is_connect(),
+            };
+            if !retry || attempt >= self.retries {
+                let response = result.map_err(ReqError::Network)?;
+                if !response.status().is_success() && response.status() != StatusCode::NOT_MODIFIED
+                {
+                    return Err(ReqError::Status(response.status()));
+                }
+                return Ok(response);
+            }
+            drop(result);
+
+            let delay = self.backoff * 2u32.pow(attempt);
+            attempt += 1;
+            log::warn!(
+                "GET {} failed (attempt {}/{}), retrying in {:?}",
+                url,
+                attempt,
+                self.retries + 1,
+                delay
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// GETs `url` and returns the body as text, served from the cache while fresh.
+    pub async fn get_text(&self, url: &str) -> Result<String, ReqError> {
+        let validators = match self.cache.lookup(url) {
+            Lookup::Fresh(body) => return Ok(body),
+            Lookup::Stale(validators) => Some(validators),
+            Lookup::Missing => None,
+        };
+        let response = self.send(url, validators.as_ref()).await?;
+        if response.status() == StatusCode::NOT_MODIFIED {
+            if let Some(body) = self.cache.revalidate(url) {
+                return Ok(body);
+            }
+            // Flushed while we were asking, so fetch it again.
+            return self.get_text_uncached(url).await;
+        }
+        let validators = Validators::from_headers(response.headers());
+        let body = response.text().await.map_err(ReqError::Network)?;
+        self.cache.store(url, &body, validators);
+        Ok(body)
+    }
+
+    /// GETs `url` and deserializes the JSON body into `T`.
+    pub async fn get_json<T: DeserializeOwned>(&self, url: &str) -> Result<T, ReqError> {
+        let text = self.get_text(url).await?;
+        serde_json::from_str(text.trim()).map_err(ReqError::Decode)
+    }
+
+    /// Like [`Http::get_text`], for endpoints that answer differently every time.
+    pub async fn get_text_uncached(&self, url: &str) -> Result<String, ReqError> {
+        self.get(url).await?.text().await.map_err(ReqError::Network)
+    }
+
+    /// Like [`Http::get_json`], for endpoints that answer differently every time.
+    pub async fn get_json_uncached<T: DeserializeOwned>(&self, url: &str) -> Result<T, ReqError> {
+        let text = self.get_text_uncached(url).await?;
+        serde_json::from_str(text.trim()).map_err(ReqError::Decode)
+    }
+}
+
+/// `base` with `params` appended as its query string, encoded so that user
+/// input can't change the request.
+pub fn with_query(base: &str, params: &[(&str, &str)]) -> String {
+    reqwest::Url::parse_with_params(base, params)
+        .expect("base URLs are constants")
+        .into()
+}
+
+/// `base` with `segments` appended to its path, each percent-encoded so
+/// that user input can't reach another endpoint. `.` and `..` segments are
+/// dropped, callers should still validate what they pass.
+pub fn with_path(base: &str, segments: &[&str]) -> String {
+    let mut url = reqwest::Url::parse(base).expect("base URLs are constants");
+    url.path_segments_mut()
+        .expect("base URLs are constants")
+        .pop_if_empty()
+        .extend(segments);
+    url.into()
+}
+
+/// Headers a cached response can be revalidated with.
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct Validators {
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl Validators {
+    fn from_headers(headers: &HeaderMap) -> Self {
+        let get = |name| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_owned)
+        };
+        Validators {
+            etag: get(ETAG),
+            last_modified: get(LAST_MODIFIED),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    body: String,
+    validators: Validators,
+    fetched: SystemTime,
+    expires: SystemTime,
+    /// Value of [`Entries::clock`] when last used, for LRU eviction
+    #[serde(skip)]
+    used: u64,
+}
+
+#[derive(Default)]
+struct Entries {
+    map: HashMap<String, Entry>,
+    clock: u64,
+}
+
+enum Lookup {
+    Fresh(String),
+    Stale(Validators),
+    Missing,
+}
+
+/// Counters shown by `k.cache`.
+pub struct CacheStats {
+    pub entries: usize,
+    pub capacity: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub revalidated: u64,
+}
+
+/// A cached URL as listed by `k.cache`.
+pub struct CachedUrl {
+    pub url: String,
+    pub age: Duration,
+    /// `None` once the entry has to be revalidated
+    pub fresh_for: Option<Duration>,
+}
+
+/// In-memory LRU cache of response bodies, keyed by URL.
+///
+/// With `persist` set, changes are written out by [`ResponseCache::persist`]
+/// every now and then rather than on every request.
+pub struct ResponseCache {
+    entries: Mutex<Entries>,
+    capacity: usize,
+    default_ttl: Duration,
+    /// URL prefixes with their TTL, longest first
+    ttl: Vec<(String, Duration)>,
+    persist: Option<PathBuf>,
+    /// Changed since it was last persisted
+    dirty: AtomicBool,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    revalidated: AtomicU64,
+}
+
+impl ResponseCache {
+    pub fn new(config: &CacheConfig) -> Self {
+        let mut ttl = config
+            .ttl
+            .iter()
+            .map(|(prefix, secs)| (prefix.clone(), Duration::from_secs(*secs)))
+            .collect::<Vec<_>>();
+        ttl.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()));
+
+        let persist = config.persist.as_ref().map(PathBuf::from);
+        let mut entries = Entries::default();
+        if let Some(path) = &persist {
+            match fs::read_to_string(path) {
+                Ok(json) => match serde_json::from_str(&json) {
+                    Ok(map) => entries.map = map,
+                    Err(e) => log::warn!("Ignoring unreadable cache {}: {}", path.display(), e),
+                },
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => log::warn!("Failed to read cache {}: {}", path.display(), e),
+            }
+        }
+
+        ResponseCache {
+            entries: Mutex::new(entries),
+            capacity: config.capacity,
+            default_ttl: Duration::from_secs(config.default_ttl_secs),
+            ttl,
+            persist,
+            dirty: AtomicBool::new(false),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            revalidated: AtomicU64::new(0),
+        }
+    }
+
+    fn ttl_for(&self, url: &str) -> Duration {
+        self.ttl
+            .iter()
+            .find(|(prefix, _)| url.starts_with(prefix.as_str()))
+            .map_or(self.default_ttl, |(_, ttl)| *ttl)
+    }
+
+    fn lookup(&self, url: &str) -> Lookup {
+        if self.ttl_for(url).is_zero() {
+            return Lookup::Missing;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        entries.clock += 1;
+        let clock = entries.clock;
+        let lookup = match entries.map.get_mut(url) {
+            Some(entry) => {
+                entry.used = clock;
+                if entry.expires > SystemTime::now() {
+                    Lookup::Fresh(entry.body.clone())
+                } else {
+                    Lookup::Stale(entry.validators.clone())
+                }
+            }
+            None => Lookup::Missing,
+        };
+        let counter = match &lookup {
+            Lookup::Fresh(_) => &self.hits,
+            _ => &self.misses,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        lookup
+    }
+
+    /// Marks a stale entry as fresh again after a `304 Not Modified`.
+    fn revalidate(&self, url: &str) -> Option<String> {
+        let ttl = self.ttl_for(url);
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries.map.get_mut(url)?;
+        entry.expires = SystemTime::now() + ttl;
+        let body = entry.body.clone();
+        self.revalidated.fetch_add(1, Ordering::Relaxed);
+        self.dirty.store(true, Ordering::Relaxed);
+        Some(body)
+    }
+
+    fn store(&self, url: &str, body: &str, validators: Validators) {
+        let ttl = self.ttl_for(url);
+        if ttl.is_zero() {
+            return;
+        }
+        let now = SystemTime::now();
+        let mut entries = self.entries.lock().unwrap();
+        entries.clock += 1;
+        let used = entries.clock;
+        entries.map.insert(
+            url.to_owned(),
+            Entry {
+                body: body.to_owned(),
+                validators,
+                fetched: now,
+                expires: now + ttl,
+                used,
+            },
+        );
+        while entries.map.len() > self.capacity {
+            let oldest = entries
+                .map
+                .iter()
+                .min_by_key(|(_, entry)| entry.used)
+                .map(|(url, _)| url.clone());
+            match oldest {
+                Some(url) => entries.map.remove(&url),
+                None => break,
+            };
+        }
+        self.dirty.store(true, Ordering::Relaxed);
+    }
+
+    /// Drops every entry, or only those whose URL starts with `prefix`.
+    ///
+    /// Returns how many entries were dropped.
+    pub fn flush(&self, prefix: Option<&str>) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        let before = entries.map.len();
+        match prefix {
+            Some(prefix) => entries.map.retain(|url, _| !url.starts_with(prefix)),
+            None => entries.map.clear(),
+        }
+        let dropped = before - entries.map.len();
+        if dropped > 0 {
+            self.dirty.store(true, Ordering::Relaxed);
+        }
+        dropped
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            entries: self.entries.lock().unwrap().map.len(),
+            capacity: self.capacity,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            revalidated: self.revalidated.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Cached URLs, sorted.
+    pub fn urls(&self) -> Vec<CachedUrl> {
+        let now = SystemTime::now();
+        let entries = self.entries.lock().unwrap();
+        let mut urls = entries
+            .map
+            .iter()
+            .map(|(url, entry)| CachedUrl {
+                url: url.clone(),
+                age: now.duration_since(entry.fetched).unwrap_or_default(),
+                fresh_for: entry.expires.duration_since(now).ok(),
+            })
+            .collect::<Vec<_>>();
+        urls.sort_by(|a, b| a.url.cmp(&b.url));
+        urls
+    }
+
+    /// Writes the cache to the `persist` file if it changed since the last
+    /// time, off the runtime thread.
+    pub async fn persist(&self) {
+        let Some(path) = self.persist.clone() else {
+            return;
+        };
+        if !self.dirty.swap(false, Ordering::Relaxed) {
+            return;
+        }
+        let json = serde_json::to_string(&self.entries.lock().unwrap().map);
+        let result = match json {
+            Ok(json) => task::spawn_blocking(move || {
+                let tmp = path.with_extension("tmp");
+                fs::write(&tmp, json)?;
+                fs::rename(&tmp, &path)
+            })
+            .await
+            .unwrap_or_else(|e| Err(io::Error::other(e))),
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = result {
+            // Try again next time.
+            self.dirty.store(true, Ordering::Relaxed);
+            log::warn!("Failed to save the response cache: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::StubServer;
+
+    /// A client retrying twice without waiting long, and caching nothing.
+    fn http(stub: &StubServer) -> Http {
+        let mut config = HttpConfig {
+            retries: 2,
+            backoff_ms: 1,
+            ..HttpConfig::default()
+        };
+        config.cache.default_ttl_secs = 0;
+        config.cache.ttl.clear();
+        let mut http = Http::new(&config).unwrap();
+        http.redirect("https://example.com", stub.url());
+        http
+    }
+
+    #[test]
+    fn path_segments_are_encoded() {
+        assert_eq!(
+            with_path("https://ipinfo.io/", &["1.1.1.1"]),
+            "https://ipinfo.io/1.1.1.1"
+        );
+        assert_eq!(
+            with_path("https://ipinfo.io", &["a/b?token=x#y"]),
+            "https://ipinfo.io/a%2Fb%3Ftoken=x%23y"
+        );
+        assert_eq!(
+            with_path("https://example.com/info", &["..", "x", "x.json"]),
+            "https://example.com/info/x/x.json"
+        );
+    }
+
+    #[tokio::test]
+    async fn server_errors_are_retried() {
+        let stub = StubServer::start(&[("/down", 503, "")]).await;
+        let result = http(&stub).get_text("https://example.com/down").await;
+        assert!(matches!(
+            result,
+            Err(ReqError::Status(StatusCode::SERVICE_UNAVAILABLE))
+        ));
+        assert_eq!(stub.requests(), ["/down", "/down", "/down"]);
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_not_retried() {
+        let stub = StubServer::start(&[]).await;
+        let error = http(&stub)
+            .get_text("https://example.com/missing")
+            .await
+            .unwrap_err();
+        assert!(error.is_not_found());
+        assert_eq!(error.to_string(), "the server answered 404 Not Found");
+        assert_eq!(stub.requests(), ["/missing"]);
+    }
+
+    #[tokio::test]
+    async fn unexpected_bodies_are_decode_errors() {
+        let stub = StubServer::start(&[("/html", 200, "<html>")]).await;
+        let http = http(&stub);
+        let result = http
+            .get_json::<serde_json::Value>("https://example.com/html")
+            .await;
+        assert!(matches!(result, Err(ReqError::Decode(_))));
+        assert_eq!(stub.requests(), ["/html"]);
+    }
+
+    #[tokio::test]
+    async fn slow_servers_time_out() {
+        // Accepts connections but never answers.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/slow", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut open = Vec::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                open.push(stream);
+            }
+        });
+        let config = HttpConfig {
+            timeout_secs: 1,
+            retries: 0,
+            ..HttpConfig::default()
+        };
+        let error = Http::new(&config).unwrap().get(&url).await.unwrap_err();
+        assert!(matches!(&error, ReqError::Network(e) if e.is_timeout()));
+        assert_eq!(error.to_string(), "the request timed out");
+    }
+
+    fn cache_config(capacity: usize) -> CacheConfig {
+        CacheConfig {
+            capacity,
+            default_ttl_secs: 60,
+            ttl: HashMap::from([("https://live/".to_owned(), 0)]),
+            persist: None,
+        }
+    }
+
+    fn fresh(cache: &ResponseCache, url: &str) -> Option<String> {
+        match cache.lookup(url) {
+            Lookup::Fresh(body) => Some(body),
+            _ => None,
+        }
+    }
+
+    /// Makes the entry for `url` outlive its TTL.
+    fn expire(cache: &ResponseCache, url: &str) {
+        let mut entries = cache.entries.lock().unwrap();
+        entries.map.get_mut(url).unwrap().expires = SystemTime::now() - Duration::from_secs(1);
+    }
+
+    #[test]
+    fn entries_go_stale_after_their_ttl() {
+        let cache = ResponseCache::new(&cache_config(8));
+        cache.store("https://a/", "a", Validators::default());
+        assert_eq!(fresh(&cache, "https://a/").as_deref(), Some("a"));
+
+        expire(&cache, "https://a/");
+        assert!(matches!(cache.lookup("https://a/"), Lookup::Stale(_)));
+
+        // A TTL of 0 keeps the prefix out of the cache.
+        cache.store("https://live/x", "x", Validators::default());
+        assert!(matches!(cache.lookup("https://live/x"), Lookup::Missing));
+    }
+
+    #[test]
+    fn least_recently_used_entries_are_evicted() {
+        let cache = ResponseCache::new(&cache_config(2));
+        cache.store("https://a/", "a", Validators::default());
+        cache.store("https://b/", "b", Validators::default());
+        assert!(fresh(&cache, "https://a/").is_some());
+        cache.store("https://c/", "c", Validators::default());
+
+        assert!(fresh(&cache, "https://a/").is_some());
+        assert!(matches!(cache.lookup("https://b/"), Lookup::Missing));
+        assert!(fresh(&cache, "https://c/").is_some());
+        assert_eq!(cache.stats().entries, 2);
+    }
+
+    #[tokio::test]
+    async fn stale_entries_are_revalidated() {
+        let stub = StubServer::start(&[("/data", 304, "")]).await;
+        let mut http = Http::new(&HttpConfig::default()).unwrap();
+        http.redirect("https://example.com", stub.url());
+        let url = "https://example.com/data";
+        let validators = Validators {
+            etag: Some("\"v1\"".to_owned()),
+            last_modified: None,
+        };
+        http.cache.store(url, "cached", validators);
+        expire(&http.cache, url);
+
+        assert_eq!(http.get_text(url).await.unwrap(), "cached");
+        assert_eq!(stub.requests(), ["/data"]);
+        assert_eq!(http.cache.stats().revalidated, 1);
+        assert_eq!(fresh(&http.cache, url).as_deref(), Some("cached"));
+    }
+
+    #[tokio::test]
+    async fn persisted_entries_are_reloaded() {
+        let path =
+            std::env::temp_dir().join(format!("knight-bot-cache-{}.json", std::process::id()));
+        let mut config = cache_config(8);
+        config.persist = Some(path.to_string_lossy().into_owned());
+
+        let cache = ResponseCache::new(&config);
+        cache.store("https://a/", "a", Validators::default());
+        cache.persist().await;
+        let reloaded = ResponseCache::new(&config);
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(fresh(&reloaded, "https://a/").as_deref(), Some("a"));
+    }
 }
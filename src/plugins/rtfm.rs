@@ -4,38 +4,54 @@
 //! SPDX-License-Identifier: MIT
 //!
 
-// Description: Sends a RTFM text.
+use crate::app::AppContext;
+use crate::plugins::{error::BotError, BoxFuture, Category, Context, Plugin};
+use crate::transport::{Button, Incoming, Outgoing};
+use std::sync::Arc;
 
-use grammers_client::{
-    button, reply_markup,
-    types::{InputMessage, Message},
-    Client,
-};
+type Result = std::result::Result<(), BotError>;
 
-type Result = std::result::Result<(), Box<dyn std::error::Error>>;
+pub struct Rtfm;
 
-pub async fn knightcmd_rtfm(client: Client, message: Message) -> Result {
+impl Plugin for Rtfm {
+    fn name(&self) -> &'static str {
+        "rtfm"
+    }
+
+    fn description(&self) -> &'static str {
+        "Sends a RTFM text."
+    }
+
+    fn category(&self) -> Category {
+        Category::Fun
+    }
+
+    fn run(&self, ctx: Context) -> BoxFuture {
+        Box::pin(knightcmd_rtfm(ctx.app, ctx.message))
+    }
+}
+
+pub async fn knightcmd_rtfm(app: Arc<AppContext>, message: Incoming) -> Result {
     if let Some(id) = message.reply_to_message_id() {
-        client
-            .send_message(
+        app.transport
+            .send(
                 message.chat(),
-                InputMessage::html("How bout you...")
+                Outgoing::html("How bout you...")
                     .reply_to(Some(id))
-                    .reply_markup(&reply_markup::inline(vec![vec![button::url(
+                    .buttons(vec![vec![Button::url(
                         "Read the fucking manual",
                         "https://readthefuckingmanual.com",
-                    )]])),
+                    )]]),
             )
             .await?;
     } else {
-        message
+        app.transport
             .reply(
-                InputMessage::html("How bout you...").reply_markup(&reply_markup::inline(vec![
-                    vec![button::url(
-                        "Read the fucking manual",
-                        "https://readthefuckingmanual.com",
-                    )],
-                ])),
+                &message,
+                Outgoing::html("How bout you...").buttons(vec![vec![Button::url(
+                    "Read the fucking manual",
+                    "https://readthefuckingmanual.com",
+                )]]),
             )
             .await?;
     }
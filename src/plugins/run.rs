@@ -4,14 +4,35 @@
 //! SPDX-License-Identifier: MIT
 //!
 
-// Description: Runnns :)
-
-use grammers_client::types::{InputMessage, Message};
+use crate::app::AppContext;
+use crate::plugins::{error::BotError, BoxFuture, Category, Context, Plugin};
+use crate::transport::{Incoming, Outgoing};
+use std::sync::Arc;
 use std::time::Instant;
 
-type Result = std::result::Result<(), Box<dyn std::error::Error>>;
+type Result = std::result::Result<(), BotError>;
+
+pub struct Run;
+
+impl Plugin for Run {
+    fn name(&self) -> &'static str {
+        "run"
+    }
+
+    fn description(&self) -> &'static str {
+        "Runnns :)"
+    }
+
+    fn category(&self) -> Category {
+        Category::Fun
+    }
+
+    fn run(&self, ctx: Context) -> BoxFuture {
+        Box::pin(knightcmd_run(ctx.app, ctx.message))
+    }
+}
 
-pub async fn knightcmd_run(message: Message) -> Result {
+pub async fn knightcmd_run(app: Arc<AppContext>, message: Incoming) -> Result {
     let start = Instant::now();
     let elapsed = start.elapsed();
     let sec = elapsed.subsec_nanos() % 3;
@@ -26,8 +47,8 @@ pub async fn knightcmd_run(message: Message) -> Result {
     } else {
         msg = c;
     }
-    message
-        .reply(InputMessage::html(format!("<b>{}</b>", msg)))
+    app.transport
+        .reply(&message, Outgoing::html(format!("<b>{}</b>", msg)))
         .await?;
     return Ok(());
 }
@@ -4,38 +4,49 @@
 //! SPDX-License-Identifier: MIT
 //!
 
-// Description: Provides the link to the source code of this bot.
+use crate::app::AppContext;
+use crate::plugins::{error::BotError, BoxFuture, Context, Plugin};
+use crate::transport::{Button, Incoming, Outgoing};
+use std::sync::Arc;
 
-use grammers_client::{
-    button, reply_markup,
-    types::{InputMessage, Message},
-    Client,
-};
+type Result = std::result::Result<(), BotError>;
 
-type Result = std::result::Result<(), Box<dyn std::error::Error>>;
+pub struct Sauce;
 
-pub async fn knightcmd_sauce(client: Client, message: Message) -> Result {
+impl Plugin for Sauce {
+    fn name(&self) -> &'static str {
+        "sauce"
+    }
+
+    fn description(&self) -> &'static str {
+        "Provides the link to the source code of this bot."
+    }
+
+    fn run(&self, ctx: Context) -> BoxFuture {
+        Box::pin(knightcmd_sauce(ctx.app, ctx.message))
+    }
+}
+
+pub async fn knightcmd_sauce(app: Arc<AppContext>, message: Incoming) -> Result {
     if let Some(id) = message.reply_to_message_id() {
-        client
-            .send_message(
+        app.transport
+            .send(
                 message.chat(),
-                InputMessage::html("You asked for it, so here you go!")
+                Outgoing::html("You asked for it, so here you go!")
                     .reply_to(Some(id))
-                    .reply_markup(&reply_markup::inline(vec![vec![button::url(
+                    .buttons(vec![vec![Button::url(
                         "sauce",
                         "https://github.com/cyberknight777/knight-bot",
-                    )]])),
+                    )]]),
             )
             .await?;
     } else {
-        message
+        app.transport
             .reply(
-                InputMessage::html("You asked for it, so here you go!").reply_markup(
-                    &reply_markup::inline(vec![vec![button::url(
-                        "sauce",
-                        "https://github.com/cyberknight777/knight-bot",
-                    )]]),
-                ),
+                &message,
+                Outgoing::html("You asked for it, so here you go!").buttons(vec![vec![
+                    Button::url("sauce", "https://github.com/cyberknight777/knight-bot"),
+                ]]),
             )
             .await?;
     }
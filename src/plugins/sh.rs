@@ -4,30 +4,458 @@
 //! SPDX-License-Identifier: MIT
 //!
 
-use grammers_client::types::{InputMessage, Message};
-use std::process::Command;
+use crate::app::AppContext;
+use crate::cfg::Role;
+use crate::jobs::{JobId, Jobs};
+use crate::plugins::{
+    args::{Kind, Param},
+    error::BotError,
+    html,
+    output::Output,
+    BoxFuture, Category, Context, Plugin,
+};
+use crate::transport::{Incoming, Outgoing};
+use std::env;
+use std::os::unix::process::CommandExt;
+use std::process::{self, ExitStatus, Stdio};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncReadExt;
+use tokio::process::Command;
+use tokio::time;
 
-type Result = std::result::Result<(), Box<dyn std::error::Error>>;
+type Result = std::result::Result<(), BotError>;
 
-pub async fn knightcmd_sh(message: Message, kcmd: String) -> Result {
+pub struct Sh;
+
+impl Plugin for Sh {
+    fn name(&self) -> &'static str {
+        "sh"
+    }
+
+    fn description(&self) -> &'static str {
+        "Executes a shell command, reporting progress as it runs."
+    }
+
+    fn category(&self) -> Category {
+        Category::DevTools
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["uname -a"]
+    }
+
+    fn params(&self) -> &'static [Param] {
+        const PARAMS: &[Param] = &[Param::optional("command", Kind::Rest)];
+        PARAMS
+    }
+
+    fn role(&self) -> Role {
+        Role::Owner
+    }
+
+    fn run(&self, ctx: Context) -> BoxFuture {
+        Box::pin(knightcmd_sh(
+            ctx.app,
+            ctx.message,
+            ctx.parsed.get("command").unwrap_or_default().to_owned(),
+        ))
+    }
+}
+
+/// Upper bound on how much of each stream is kept in memory.
+const MAX_OUTPUT: usize = 1024 * 1024;
+
+/// Lines of each stream shown while a command is still running.
+const TAIL_LINES: usize = 10;
+
+/// Characters of each stream shown while a command is still running, so
+/// both fit in a message even if their lines are long.
+const TAIL_CHARS: usize = 1500;
+
+enum Outcome {
+    Exited(ExitStatus),
+    TimedOut,
+    Cancelled,
+}
+
+pub async fn knightcmd_sh(app: Arc<AppContext>, message: Incoming, kcmd: String) -> Result {
     if kcmd.trim().is_empty() {
-        message.reply(InputMessage::html("Dude! With all due respect that you're my maker and all, give me a <b>proper command</b> to run!")).await?;
+        app.transport.reply(&message, Outgoing::html("Dude! With all due respect that you're my maker and all, give me a <b>proper command</b> to run!")).await?;
         return Ok(());
     }
-    let command = Command::new("bash")
+    let config = app.config();
+    let sh = &config.sh;
+
+    // In a process group of its own, so pipelines and background jobs can
+    // be killed along with the shell.
+    let mut command = process::Command::new("bash");
+    command
         .arg("-c")
-        .arg(kcmd)
-        .output()
-        .expect("Failed to execute command!");
-    let output = String::from_utf8_lossy(&command.stdout).to_string();
-    let status = command.status;
-    let error = String::from_utf8_lossy(&command.stderr).to_string();
-    let input_message = InputMessage::html(format!(
-        "<code>{}</code>\n\n<b>{}</b>\n\n<code>{}</code>",
-        output.trim(),
-        status,
-        error.trim()
-    ));
-    message.reply(input_message).await?;
+        .arg(&kcmd)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0);
+    if let Some(dir) = &sh.workdir {
+        command.current_dir(dir);
+    }
+    if sh.clear_env {
+        command.env_clear();
+        if let Ok(path) = env::var("PATH") {
+            command.env("PATH", path);
+        }
+    }
+    command.envs(&sh.env);
+    let mut command = Command::from(command);
+    command.kill_on_drop(true);
+
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            app.transport
+                .reply(
+                    &message,
+                    Outgoing::html(format!(
+                        "<b>Failed to execute command!</b>\n<code>{}</code>",
+                        html::escape(&e.to_string())
+                    )),
+                )
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let mut cleanup = Cleanup {
+        jobs: &app.jobs,
+        job: None,
+        group: child.id(),
+    };
+
+    let msg = app
+        .transport
+        .reply(&message, Outgoing::html("<b>Running...</b>"))
+        .await?;
+    let job = (message.chat().id(), msg.id());
+    let mut cancel = app.jobs.start(job);
+    cleanup.job = Some(job);
+
+    let mut out = child.stdout.take().unwrap();
+    let mut err = child.stderr.take().unwrap();
+    let (mut out_buf, mut err_buf) = ([0; 8192], [0; 8192]);
+    let (mut output, mut error) = (Capture::default(), Capture::default());
+    let (mut out_done, mut err_done) = (false, false);
+
+    let started = Instant::now();
+    let deadline = time::sleep(Duration::from_secs(sh.timeout_secs));
+    tokio::pin!(deadline);
+    let mut ticker = time::interval(Duration::from_secs(sh.progress_interval_secs));
+    ticker.tick().await;
+    let mut shown = 0;
+
+    let outcome = loop {
+        tokio::select! {
+            read = out.read(&mut out_buf), if !out_done => match read {
+                Ok(0) | Err(_) => out_done = true,
+                Ok(n) => output.push(&out_buf[..n]),
+            },
+            read = err.read(&mut err_buf), if !err_done => match read {
+                Ok(0) | Err(_) => err_done = true,
+                Ok(n) => error.push(&err_buf[..n]),
+            },
+            status = child.wait(), if out_done && err_done => break Outcome::Exited(status?),
+            _ = &mut deadline => break Outcome::TimedOut,
+            _ = &mut cancel => break Outcome::Cancelled,
+            _ = ticker.tick() => {
+                if output.read + error.read == shown {
+                    continue;
+                }
+                shown = output.read + error.read;
+                let progress = Outgoing::html(format!(
+                    "<b>Running for {}s...</b>\n\n<code>{}</code>\n\n<code>{}</code>",
+                    started.elapsed().as_secs(),
+                    html::escape(&tail(&output.text())),
+                    html::escape(&tail(&error.text()))
+                ));
+                // Not queued: a progress update is not worth sitting out a
+                // flood wait for, the next tick sends a fresher one anyway.
+                if let Err(e) = app.transport.edit_now(&msg, progress).await {
+                    log::warn!("Failed to update k.sh progress: {}", e);
+                }
+            }
+        }
+    };
+
+    let mut status = match outcome {
+        Outcome::Exited(status) => status.to_string(),
+        Outcome::TimedOut => format!("Timed out after {}s, killed", sh.timeout_secs),
+        Outcome::Cancelled => "Cancelled, killed".to_owned(),
+    };
+    if output.read > MAX_OUTPUT || error.read > MAX_OUTPUT {
+        status.push_str(", output truncated");
+    }
+    if !matches!(outcome, Outcome::Exited(_)) {
+        cleanup.kill();
+        let _ = child.kill().await;
+    }
+    // The shell has been reaped, so its ID may be taken by someone else now.
+    cleanup.group = None;
+    drop(cleanup);
+    let (output, error) = (output.text(), error.text());
+    let body = if error.trim().is_empty() {
+        output.trim().to_owned()
+    } else {
+        format!("{}\n\n{}", output.trim(), error.trim())
+    };
+    Output::code(body)
+        .header(format!("<b>{}</b>", html::escape(&status)))
+        .edit(&app, &message, &msg)
+        .await?;
     return Ok(());
 }
+
+/// Forgets a `k.sh` job and kills what is left of its process group however
+/// the command ends, including errors and the handler being dropped.
+struct Cleanup<'a> {
+    jobs: &'a Jobs,
+    job: Option<JobId>,
+    /// Process group of the command, `None` once the shell has been reaped
+    group: Option<u32>,
+}
+
+impl Cleanup<'_> {
+    fn kill(&self) {
+        let Some(group) = self.group else {
+            return;
+        };
+        // SAFETY: killpg only sends a signal, it does not touch our memory.
+        if unsafe { libc::killpg(group as libc::pid_t, libc::SIGKILL) } != 0 {
+            let e = std::io::Error::last_os_error();
+            if e.raw_os_error() != Some(libc::ESRCH) {
+                log::warn!("Failed to kill k.sh process group {}: {}", group, e);
+            }
+        }
+    }
+}
+
+impl Drop for Cleanup<'_> {
+    fn drop(&mut self) {
+        if let Some(job) = self.job {
+            self.jobs.finish(job);
+        }
+        self.kill();
+    }
+}
+
+/// What a command wrote to one of its streams.
+#[derive(Default)]
+struct Capture {
+    /// The first [`MAX_OUTPUT`] bytes
+    kept: Vec<u8>,
+    /// Bytes read in total
+    read: usize,
+}
+
+impl Capture {
+    /// Keeps as much of `chunk` as fits; the rest is still read, so the
+    /// command does not block on a full pipe, but dropped.
+    fn push(&mut self, chunk: &[u8]) {
+        let room = MAX_OUTPUT - self.kept.len();
+        self.kept.extend_from_slice(&chunk[..chunk.len().min(room)]);
+        self.read += chunk.len();
+    }
+
+    fn text(&self) -> String {
+        String::from_utf8_lossy(&self.kept).into_owned()
+    }
+}
+
+/// Returns the last few lines of `text`, and of those at most
+/// [`TAIL_CHARS`] characters.
+fn tail(text: &str) -> String {
+    let lines = text.trim_end().lines().collect::<Vec<_>>();
+    let tail = lines[lines.len().saturating_sub(TAIL_LINES)..].join("\n");
+    let chars = tail.chars().count();
+    if chars <= TAIL_CHARS {
+        return tail;
+    }
+    let rest = tail
+        .chars()
+        .skip(chars - TAIL_CHARS + 1)
+        .collect::<String>();
+    format!("…{}", rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfg::{Config, ShConfig};
+    use crate::plugins::cancel::knightcmd_cancel;
+    use crate::testing::{self, Event, TestBot};
+    use std::fs;
+
+    fn bot(sh: ShConfig) -> TestBot {
+        let mut config = Config::default();
+        config.sh = sh;
+        TestBot::with_config(config)
+    }
+
+    async fn run(bot: &TestBot, command: &str) -> Vec<Event> {
+        let message = testing::message(1, &format!("k.sh {}", command));
+        knightcmd_sh(bot.app(), message, command.to_owned())
+            .await
+            .unwrap();
+        bot.take()
+    }
+
+    /// Text of the final edit, the result of the command.
+    fn result(events: &[Event]) -> &str {
+        let edit = events.iter().rev().find_map(|event| match event {
+            Event::Edited { outgoing, .. } => Some(&outgoing.text),
+            _ => None,
+        });
+        edit.expect("no result")
+    }
+
+    /// Whether process `pid` exists and has not yet exited.
+    fn running(pid: u32) -> bool {
+        match fs::read_to_string(format!("/proc/{}/stat", pid)) {
+            Ok(stat) => !stat.contains(") Z "),
+            Err(_) => false,
+        }
+    }
+
+    #[tokio::test]
+    async fn reports_output_and_exit_status() {
+        let bot = TestBot::new();
+        let events = run(&bot, "echo out; echo err >&2; exit 3").await;
+        assert_eq!(
+            events[0],
+            Event::Sent {
+                chat: testing::CHAT_ID,
+                outgoing: Outgoing::html("<b>Running...</b>").reply_to(Some(1)),
+            }
+        );
+        assert_eq!(
+            result(&events),
+            "<b>exit status: 3</b>\n\n<code>out\n\nerr</code>"
+        );
+    }
+
+    #[tokio::test]
+    async fn explains_spawn_failures() {
+        let bot = bot(ShConfig {
+            workdir: Some("/nonexistent/knight-bot".to_owned()),
+            ..ShConfig::default()
+        });
+        match run(&bot, "true").await.as_slice() {
+            [Event::Sent { outgoing, .. }] => assert!(outgoing
+                .text
+                .starts_with("<b>Failed to execute command!</b>\n<code>No such file")),
+            events => panic!("expected a single reply, got {:?}", events),
+        }
+    }
+
+    #[tokio::test]
+    async fn timeouts_kill_the_process_group() {
+        let bot = bot(ShConfig {
+            timeout_secs: 1,
+            ..ShConfig::default()
+        });
+        let events = run(&bot, "sleep 60 & echo $!; wait").await;
+        let result = result(&events);
+        let pid = result
+            .strip_prefix("<b>Timed out after 1s, killed</b>\n\n<code>")
+            .and_then(|rest| rest.strip_suffix("</code>"))
+            .and_then(|pid| pid.parse().ok())
+            .unwrap_or_else(|| panic!("unexpected result {:?}", result));
+
+        // SIGKILL is delivered asynchronously, give it a moment.
+        for _ in 0..100 {
+            if !running(pid) {
+                break;
+            }
+            time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(!running(pid), "background job {} survived", pid);
+        // The job was forgotten.
+        assert_eq!(bot.app.jobs.cancel(testing::CHAT_ID, None), 0);
+    }
+
+    #[tokio::test]
+    async fn k_cancel_kills_the_command() {
+        let bot = TestBot::new();
+        let message = testing::message(1, "k.sh sleep 60");
+        let job = tokio::spawn(knightcmd_sh(bot.app(), message, "sleep 60".to_owned()));
+        // Runs the command up to waiting for its output.
+        tokio::task::yield_now().await;
+
+        let cancel = testing::message(2, "k.cancel").in_reply_to(Some(1000));
+        knightcmd_cancel(bot.app(), cancel).await.unwrap();
+        job.await.unwrap().unwrap();
+
+        let events = bot.take();
+        assert_eq!(
+            events[1],
+            Event::Sent {
+                chat: testing::CHAT_ID,
+                outgoing: Outgoing::html("<b>Cancelled 1 job(s)!</b>").reply_to(Some(2)),
+            }
+        );
+        assert_eq!(result(&events), "<b>Cancelled, killed</b>\n\n<code></code>");
+    }
+
+    #[tokio::test]
+    async fn output_is_capped() {
+        let bot = TestBot::new();
+        let events = run(&bot, "head -c 3000000 /dev/zero | tr '\\0' y").await;
+        assert_eq!(result(&events), "<b>exit status: 0, output truncated</b>");
+        match events.last() {
+            Some(Event::Sent { outgoing, .. }) => {
+                let (_, contents) = outgoing.document.as_ref().unwrap();
+                assert_eq!(contents.len(), MAX_OUTPUT);
+            }
+            event => panic!("expected the output as a file, got {:?}", event),
+        }
+    }
+
+    #[tokio::test]
+    async fn shows_progress() {
+        let bot = bot(ShConfig {
+            progress_interval_secs: 1,
+            ..ShConfig::default()
+        });
+        let events = run(&bot, "echo started; sleep 2").await;
+        let progress = events.iter().find_map(|event| match event {
+            Event::Edited { outgoing, .. } if outgoing.text.starts_with("<b>Running for ") => {
+                Some(&outgoing.text)
+            }
+            _ => None,
+        });
+        assert_eq!(
+            progress.map(String::as_str),
+            Some("<b>Running for 1s...</b>\n\n<code>started</code>\n\n<code></code>")
+        );
+    }
+
+    #[test]
+    fn progress_shows_a_short_tail() {
+        let lines = (1..=20).map(|n| n.to_string()).collect::<Vec<_>>();
+        assert_eq!(tail(&lines.join("\n")), lines[10..].join("\n"));
+
+        let long = "y".repeat(5000);
+        let shown = tail(&long);
+        assert_eq!(shown.chars().count(), TAIL_CHARS);
+        assert!(shown.starts_with('…'));
+    }
+
+    #[test]
+    fn captures_are_capped() {
+        let mut capture = Capture::default();
+        capture.push(&vec![b'y'; MAX_OUTPUT - 1]);
+        capture.push(b"abc");
+        assert_eq!(capture.kept.len(), MAX_OUTPUT);
+        assert_eq!(capture.read, MAX_OUTPUT + 2);
+        assert!(capture.text().ends_with('a'));
+    }
+}
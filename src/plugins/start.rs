@@ -4,14 +4,31 @@
 //! SPDX-License-Identifier: MIT
 //!
 
-// Description: Checks if I'm alive.
+use crate::app::AppContext;
+use crate::plugins::{error::BotError, BoxFuture, Context, Plugin};
+use crate::transport::Incoming;
+use std::sync::Arc;
 
-use grammers_client::types::Message;
+type Result = std::result::Result<(), BotError>;
 
-type Result = std::result::Result<(), Box<dyn std::error::Error>>;
+pub struct Start;
 
-pub async fn knightcmd_start(message: Message) -> Result {
-    let msg = "Heya! Type /help to see what I can do!";
-    message.reply(msg).await?;
+impl Plugin for Start {
+    fn name(&self) -> &'static str {
+        "start"
+    }
+
+    fn description(&self) -> &'static str {
+        "Checks if I'm alive."
+    }
+
+    fn run(&self, ctx: Context) -> BoxFuture {
+        Box::pin(knightcmd_start(ctx.app, ctx.message, ctx.prefix))
+    }
+}
+
+pub async fn knightcmd_start(app: Arc<AppContext>, message: Incoming, prefix: String) -> Result {
+    let msg = format!("Heya! Type {}help to see what I can do!", prefix);
+    app.transport.reply(&message, msg.into()).await?;
     return Ok(());
 }
@@ -6,16 +6,36 @@
 //! - Adreno Freedreno/Mainline GPU usage
 //! - Battery percentage
 //! - Kernel version
+//! - Outbox queue depth and flood waits
 //!
 //! Optimized for Mainline Linux on SM8150 (Xiaomi Raphael)
 
-use grammers_client::types::{InputMessage, Message};
-use sysinfo::System;
+use crate::app::AppContext;
+use crate::plugins::{error::BotError, html, BoxFuture, Context, Plugin};
+use crate::transport::{Incoming, Outgoing};
+use std::sync::Arc;
 use std::time::Duration;
+use sysinfo::System;
+
+type Result = std::result::Result<(), BotError>;
+
+pub struct Status;
+
+impl Plugin for Status {
+    fn name(&self) -> &'static str {
+        "status"
+    }
 
-type Result = std::result::Result<(), Box<dyn std::error::Error>>;
+    fn description(&self) -> &'static str {
+        "Shows system status of the host."
+    }
+
+    fn run(&self, ctx: Context) -> BoxFuture {
+        Box::pin(knightcmd_status(ctx.app, ctx.message))
+    }
+}
 
-pub async fn knightcmd_status(message: Message) -> Result {
+pub async fn knightcmd_status(app: Arc<AppContext>, message: Incoming) -> Result {
     let mut sys = System::new_all();
 
     // 1. CPU: Average usage over ~1.5s to avoid wakeup spikes on mobile SoCs
@@ -32,6 +52,9 @@ pub async fn knightcmd_status(message: Message) -> Result {
     let battery = read_battery_percentage();
     let kernel = System::kernel_version().unwrap_or_else(|| "unknown".into());
 
+    // 5. Messages waiting to be sent by the bot
+    let outbox = app.transport.stats().unwrap_or_default();
+
     let text = format!(
         "🖥 <b>System Status</b>\n\
          ─────────────────\n\
@@ -39,16 +62,21 @@ pub async fn knightcmd_status(message: Message) -> Result {
          <b>Memory:</b> {} / {} MiB\n\
          <b>GPU (Adreno 640):</b> {}\n\
          <b>Battery:</b> {}\n\
-         <b>Kernel:</b> {}",
+         <b>Kernel:</b> {}\n\
+         <b>Outbox:</b> {} queued in {} chat(s), {} sent, {} flood wait(s)",
         cpu_usage,
         used_mem,
         total_mem,
         gpu,
-        battery,
-        kernel
+        html::escape(&battery),
+        html::escape(&kernel),
+        outbox.queued,
+        outbox.chats,
+        outbox.sent,
+        outbox.flood_waits
     );
 
-    message.reply(InputMessage::html(text)).await?;
+    app.transport.reply(&message, Outgoing::html(text)).await?;
     Ok(())
 }
 
@@ -70,7 +98,6 @@ async fn read_cpu_avg(sys: &mut System) -> f32 {
     total / samples as f32
 }
 
-
 /// Read Freedreno GPU stats via devfreq and drm sysfs
 fn read_freedreno_gpu() -> Option<String> {
     let base = "/sys/class/devfreq/2c00000.gpu";
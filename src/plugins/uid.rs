@@ -4,29 +4,43 @@
 //! SPDX-License-Identifier: MIT
 //!
 
-// Description: Gets UserID and ChatID.
+use crate::app::AppContext;
+use crate::plugins::{error::BotError, html, BoxFuture, Context, Plugin};
+use crate::transport::{Incoming, Outgoing};
+use std::sync::Arc;
 
-use grammers_client::{
-    types::{InputMessage, Message},
-    Client,
-};
+type Result = std::result::Result<(), BotError>;
 
-type Result = std::result::Result<(), Box<dyn std::error::Error>>;
+pub struct Uid;
 
-pub async fn knightcmd_uid(client: Client, message: Message) -> Result {
+impl Plugin for Uid {
+    fn name(&self) -> &'static str {
+        "uid"
+    }
+
+    fn description(&self) -> &'static str {
+        "Gets UserID and ChatID."
+    }
+
+    fn run(&self, ctx: Context) -> BoxFuture {
+        Box::pin(knightcmd_uid(ctx.app, ctx.message))
+    }
+}
+
+pub async fn knightcmd_uid(app: Arc<AppContext>, message: Incoming) -> Result {
     if let Some(id) = message.reply_to_message_id() {
-        if let Some(reply_to_msg) = client.get_reply_to_message(&message).await? {
+        if let Some(reply_to_msg) = app.transport.reply_to_message(&message).await? {
             if let Some(sender) = reply_to_msg.sender() {
-                client
-                    .send_message(
+                app.transport
+                    .send(
                         message.chat(),
-                        InputMessage::html(format!(
+                        Outgoing::html(format!(
                             "Your ID: <code>{}</code>
 ChatID: <code>-100{}</code>
 {}'s ID: <code>{}</code>",
                             message.sender().unwrap().id(),
                             message.chat().id(),
-                            sender.name(),
+                            html::escape(sender.name()),
                             sender.id()
                         ))
                         .reply_to(Some(id)),
@@ -35,14 +49,52 @@ ChatID: <code>-100{}</code>
             }
         }
     } else {
-        message
-            .reply(InputMessage::html(format!(
-                "Your ID: <code>{}</code>
+        app.transport
+            .reply(
+                &message,
+                Outgoing::html(format!(
+                    "Your ID: <code>{}</code>
 ChatID: <code>-100{}</code>",
-                message.sender().unwrap().id(),
-                message.chat().id()
-            )))
+                    message.sender().unwrap().id(),
+                    message.chat().id()
+                )),
+            )
             .await?;
     }
     return Ok(());
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, TestBot};
+
+    #[tokio::test]
+    async fn shows_the_ids() {
+        let bot = TestBot::new();
+        knightcmd_uid(bot.app(), testing::message(1, "/uid"))
+            .await
+            .unwrap();
+        assert_eq!(
+            bot.take_sent(),
+            Outgoing::html("Your ID: <code>42</code>\nChatID: <code>-100100</code>")
+                .reply_to(Some(1))
+        );
+    }
+
+    #[tokio::test]
+    async fn shows_the_id_of_whoever_was_replied_to() {
+        let bot = TestBot::new();
+        let replied = Incoming::new(1, testing::group(), Some(testing::user(7)), "hi");
+        bot.transport.remember(replied);
+        let message = testing::message(2, "/uid").in_reply_to(Some(1));
+        knightcmd_uid(bot.app(), message).await.unwrap();
+        assert_eq!(
+            bot.take_sent(),
+            Outgoing::html(
+                "Your ID: <code>42</code>\nChatID: <code>-100100</code>\nUser 7's ID: <code>7</code>"
+            )
+            .reply_to(Some(1))
+        );
+    }
+}
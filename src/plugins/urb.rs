@@ -4,64 +4,193 @@
 //! SPDX-License-Identifier: MIT
 //!
 
-// Description: Gets the definition of word from urban dictionary.
+use crate::app::AppContext;
+use crate::plugins::{
+    args::{Args, Kind, Param},
+    error::BotError,
+    html::Html,
+    inline::{Inline, InlineFuture, InlineResult},
+    req::{self, ReqError},
+    BoxFuture, Category, Context, Plugin,
+};
+use crate::transport::{Incoming, Outgoing};
+use serde_json::Value;
+use std::sync::Arc;
 
-use crate::plugins;
-use grammers_client::types::{InputMessage, Message};
+type Result = std::result::Result<(), BotError>;
 
-type Result = std::result::Result<(), Box<dyn std::error::Error>>;
+pub struct Urb;
 
-async fn get_def(taxt: &String) -> Option<String> {
-    let url = format!("https://api.urbandictionary.com/v0/define?term={}", taxt);
-    let response = match plugins::req::make_request(url).await {
-        Some(val) => val,
-        None => return None,
-    };
-    if response["list"].as_array().unwrap().is_empty() {
-        return Some(String::from("No definition found!"));
+impl Plugin for Urb {
+    fn name(&self) -> &'static str {
+        "urb"
+    }
+
+    fn description(&self) -> &'static str {
+        "Gets the definition of word from urban dictionary."
+    }
+
+    fn category(&self) -> Category {
+        Category::Fun
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["yeet"]
+    }
+
+    fn params(&self) -> &'static [Param] {
+        const PARAMS: &[Param] = &[Param::optional("term", Kind::Rest)];
+        PARAMS
+    }
+
+    fn inline(&self) -> Option<&dyn Inline> {
+        Some(self)
+    }
+
+    fn run(&self, ctx: Context) -> BoxFuture {
+        Box::pin(knightcmd_urb(
+            ctx.app,
+            ctx.message,
+            ctx.parsed.get("term").unwrap_or_default().to_owned(),
+        ))
+    }
+}
+
+impl Inline for Urb {
+    fn answer(&self, app: Arc<AppContext>, args: Args) -> InlineFuture {
+        let word = args.get("term").unwrap_or_default().trim().to_owned();
+        Box::pin(async move {
+            if word.is_empty() {
+                return Err(BotError::bad_input("Give me a word to define!"));
+            }
+            let defin = get_def(&app, &word).await?;
+            let text = definition(&word, &defin);
+            Ok(vec![InlineResult::new(&word, Outgoing::html(text.build()))
+                .description(defin.replace(r#"\r\n"#, " "))])
+        })
+    }
+}
+
+fn definition(word: &str, defin: &str) -> Html {
+    Html::new()
+        .text("Definition for ")
+        .bold(word)
+        .text(" : ")
+        .italic(defin.replace(r#"\r\n"#, ""))
+}
+
+async fn get_def(app: &AppContext, taxt: &String) -> std::result::Result<String, ReqError> {
+    let url = req::with_query(
+        "https://api.urbandictionary.com/v0/define",
+        &[("term", taxt.as_str())],
+    );
+    let response = app.http.get_json::<Value>(&url).await?;
+    if response["list"]
+        .as_array()
+        .map_or(true, |list| list.is_empty())
+    {
+        return Ok(String::from("No definition found!"));
     }
     let target = &response["list"][0]["definition"];
-    Some(target.to_string().trim_matches('"').to_string())
+    Ok(target.to_string().trim_matches('"').to_string())
 }
 
-pub async fn knightcmd_urb(message: Message, word: String) -> Result {
+pub async fn knightcmd_urb(app: Arc<AppContext>, message: Incoming, word: String) -> Result {
     if word.trim().is_empty() {
-        let msg = message
-            .reply(InputMessage::html(
-                "<b>Getting definition of random word from urban dictionary...</b>",
-            ))
+        let msg = app
+            .transport
+            .reply(
+                &message,
+                Outgoing::html("<b>Getting definition of random word from urban dictionary...</b>"),
+            )
             .await?;
         let url = "http://api.urbandictionary.com/v0/random";
-        let response = plugins::req::make_request(url.to_string()).await;
-        let word = &response.clone().unwrap()["list"][0]["word"];
-        let defin = &response.clone().unwrap()["list"][0]["definition"];
-        msg.edit(InputMessage::html(format!(
-            "Definition for <b>{}</b> : <i>{}</i>",
-            word.to_string().trim_matches('"').to_string(),
-            defin
-                .to_string()
-                .trim_matches('"')
-                .to_string()
-                .replace(r#"\r\n"#, "")
-        )))
-        .await?;
+        let response = match app.http.get_json_uncached::<Value>(url).await {
+            Ok(response) => response,
+            Err(e) => {
+                log::warn!("Urban Dictionary random lookup failed: {:?}", e);
+                app.transport
+                    .edit(&msg, format!("Something went wrong, {}!", e).into())
+                    .await?;
+                return Ok(());
+            }
+        };
+        let word = &response["list"][0]["word"];
+        let defin = &response["list"][0]["definition"];
+        let text = definition(
+            word.to_string().trim_matches('"'),
+            defin.to_string().trim_matches('"'),
+        );
+        app.transport
+            .edit(&msg, Outgoing::html(text.build()))
+            .await?;
     } else {
-        let msg = message
-            .reply(InputMessage::html(
-                "<b>Getting definition of word from urban dictionary...</b>",
-            ))
+        let msg = app
+            .transport
+            .reply(
+                &message,
+                Outgoing::html("<b>Getting definition of word from urban dictionary...</b>"),
+            )
             .await?;
-        let defin = get_def(&word).await;
-        if defin.is_none() {
-            msg.edit("Something went wrong!").await?;
+        let defin = get_def(&app, &word).await;
+        if let Err(e) = &defin {
+            log::warn!("Urban Dictionary lookup for {} failed: {:?}", word, e);
+            app.transport
+                .edit(&msg, format!("Something went wrong, {}!", e).into())
+                .await?;
         } else {
-            msg.edit(InputMessage::html(format!(
-                "Definition for <b>{}</b> : <i>{}</i>",
-                word,
-                defin.unwrap().replace(r#"\r\n"#, "")
-            )))
-            .await?;
+            let text = definition(&word, &defin.unwrap());
+            app.transport
+                .edit(&msg, Outgoing::html(text.build()))
+                .await?;
         }
     }
     return Ok(());
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, Event, StubServer, TestBot};
+
+    #[tokio::test]
+    async fn edits_in_the_definition() {
+        let body =
+            r#"{"list": [{"word": "rust", "definition": "A [language]\r\nthat compiles."}]}"#;
+        let stub = StubServer::start(&[("/v0/define?term=rust", 200, body)]).await;
+        let bot = TestBot::new().redirect("https://api.urbandictionary.com", &stub);
+        let message = testing::message(1, "/urb rust");
+        knightcmd_urb(bot.app(), message, "rust".to_owned())
+            .await
+            .unwrap();
+        assert_eq!(
+            bot.take(),
+            [
+                Event::Sent {
+                    chat: testing::CHAT_ID,
+                    outgoing: Outgoing::html(
+                        "<b>Getting definition of word from urban dictionary...</b>"
+                    )
+                    .reply_to(Some(1)),
+                },
+                Event::Edited {
+                    id: 1000,
+                    outgoing: Outgoing::html(
+                        "Definition for <b>rust</b> : <i>A [language]that compiles.</i>"
+                    ),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn encodes_the_term() {
+        let body = r#"{"list": [{"definition": "Both."}]}"#;
+        let stub = StubServer::start(&[("/v0/define?term=this+%26+that%3F+%23x", 200, body)]).await;
+        let bot = TestBot::new().redirect("https://api.urbandictionary.com", &stub);
+        let defin = get_def(&bot.app, &"this & that? #x".to_owned())
+            .await
+            .unwrap();
+        assert_eq!(defin, "Both.");
+    }
+}
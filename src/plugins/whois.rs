@@ -4,27 +4,89 @@
 //! SPDX-License-Identifier: MIT
 //!
 
-// Description: Checks WHOIS information of a given URL.
-
-use grammers_client::types::{InputMessage, Message};
+use crate::app::AppContext;
+use crate::plugins::{
+    args::{Kind, Param},
+    error::BotError,
+    output::Output,
+    BoxFuture, Category, Context, Plugin,
+};
+use crate::transport::{Incoming, Outgoing};
+use std::sync::Arc;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
-type Result = std::result::Result<(), Box<dyn std::error::Error>>;
+type Result = std::result::Result<(), BotError>;
+
+pub struct Whois;
+
+impl Plugin for Whois {
+    fn name(&self) -> &'static str {
+        "whois"
+    }
+
+    fn description(&self) -> &'static str {
+        "Checks WHOIS information of a given URL."
+    }
+
+    fn category(&self) -> Category {
+        Category::Network
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["example.com", "--full example.com"]
+    }
+
+    fn params(&self) -> &'static [Param] {
+        const PARAMS: &[Param] = &[
+            Param::optional("site", Kind::Word),
+            Param::flag("full", 'f'),
+        ];
+        PARAMS
+    }
 
-pub async fn knightcmd_whois(message: Message, site: String) -> Result {
+    fn run(&self, ctx: Context) -> BoxFuture {
+        Box::pin(knightcmd_whois(
+            ctx.app,
+            ctx.message,
+            ctx.parsed.get("site").unwrap_or_default().to_owned(),
+            ctx.parsed.flag("full"),
+        ))
+    }
+}
+
+pub async fn knightcmd_whois(
+    app: Arc<AppContext>,
+    message: Incoming,
+    site: String,
+    full: bool,
+) -> Result {
     if site.trim().is_empty() {
-        message
-            .reply(InputMessage::html(
-                "Send a <b>proper URL</b> to get WHOIS information!",
-            ))
+        app.transport
+            .reply(
+                &message,
+                Outgoing::html("Send a <b>proper URL</b> to get WHOIS information!"),
+            )
             .await?;
         return Ok(());
     } else {
-        let msg = message
-            .reply(InputMessage::html(
-                "<b>Extracting WHOIS information from given link...</b>",
-            ))
+        let msg = app
+            .transport
+            .reply(
+                &message,
+                Outgoing::html("<b>Extracting WHOIS information from given link...</b>"),
+            )
             .await?;
+        if full {
+            let output = tokio::process::Command::new("whois")
+                .arg(site)
+                .output()
+                .await?
+                .stdout;
+            Output::code(String::from_utf8_lossy(&output))
+                .edit(&app, &message, &msg)
+                .await?;
+            return Ok(());
+        }
         let mut whois_process = tokio::process::Command::new("whois")
             .arg(site)
             .stdout(std::process::Stdio::piped())
@@ -45,9 +107,12 @@ pub async fn knightcmd_whois(message: Message, site: String) -> Result {
         }
         let output = grep_process.wait_with_output().await?.stdout;
         if output.is_empty() {
-            msg.edit("No WHOIS information found!").await?;
+            app.transport
+                .edit(&msg, "No WHOIS information found!".into())
+                .await?;
         } else {
-            msg.edit(format!("{}", String::from_utf8_lossy(&output)))
+            Output::code(String::from_utf8_lossy(&output))
+                .edit(&app, &message, &msg)
                 .await?;
         }
     }
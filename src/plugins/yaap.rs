@@ -4,16 +4,46 @@
 //! SPDX-License-Identifier: MIT
 //!
 
-// Description: Gets the latest YAAP release according to the device.
-
-use crate::plugins;
-use grammers_client::{
-    button, reply_markup,
-    types::{InputMessage, Message},
-    Client,
+use crate::app::AppContext;
+use crate::plugins::{
+    args::{Kind, Param},
+    error::BotError,
+    html, req, BoxFuture, Category, Context, Plugin,
 };
+use crate::transport::{Button, Incoming, Outgoing};
+use serde_json::Value;
+use std::sync::Arc;
+
+type Result = std::result::Result<(), BotError>;
+
+pub struct Yaap;
+
+impl Plugin for Yaap {
+    fn name(&self) -> &'static str {
+        "yaap"
+    }
+
+    fn description(&self) -> &'static str {
+        "Gets the latest YAAP release according to the device."
+    }
+
+    fn category(&self) -> Category {
+        Category::Android
+    }
+
+    fn params(&self) -> &'static [Param] {
+        const PARAMS: &[Param] = &[Param::optional("device", Kind::Word)];
+        PARAMS
+    }
 
-type Result = std::result::Result<(), Box<dyn std::error::Error>>;
+    fn run(&self, ctx: Context) -> BoxFuture {
+        Box::pin(knightcmd_yaap(
+            ctx.app,
+            ctx.message,
+            ctx.parsed.get("device").unwrap_or_default().to_owned(),
+        ))
+    }
+}
 
 fn get_date(filename: &str) -> Option<String> {
     if let Some(stem) = filename.strip_suffix(".zip") {
@@ -33,27 +63,33 @@ fn get_date(filename: &str) -> Option<String> {
     None
 }
 
-pub async fn knightcmd_yaap(client: Client, message: Message, device: String) -> Result {
-    if device.trim().is_empty() {
-        message
-            .reply(InputMessage::html("Provide a device <b>codename</b>!"))
+pub async fn knightcmd_yaap(app: Arc<AppContext>, message: Incoming, device: String) -> Result {
+    // Codenames go into the URL path, anything else could name another file.
+    let codename = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-';
+    if device.is_empty() || !device.chars().all(codename) {
+        app.transport
+            .reply(
+                &message,
+                Outgoing::html("Provide a device <b>codename</b>!"),
+            )
             .await?;
         return Ok(());
     }
 
-    let branch = format!(
-        "https://raw.githubusercontent.com/YAAP/device-info/master/{}/{}.json",
-        device, device
+    let file = format!("{}.json", device);
+    let branch = req::with_path(
+        "https://raw.githubusercontent.com/YAAP/device-info/master",
+        &[device.as_str(), file.as_str()],
     );
 
-    let branch_resp = plugins::req::make_request(branch.to_string()).await;
+    let branch_resp = app.http.get_json::<Value>(&branch).await;
 
     let gapps_branch;
 
     let vanilla_branch;
 
     match branch_resp {
-        Some(branch_resp) => {
+        Ok(branch_resp) => {
             gapps_branch = branch_resp["ota-branch"]
                 .to_string()
                 .trim_matches('"')
@@ -63,27 +99,45 @@ pub async fn knightcmd_yaap(client: Client, message: Message, device: String) ->
                 .trim_matches('"')
                 .to_string();
         }
-        None => {
-            message
-                .reply(InputMessage::html(
-                    "Failed to get YAAP release information! (OTA Branch)",
-                ))
+        Err(e) if e.is_not_found() => {
+            app.transport
+                .reply(
+                    &message,
+                    Outgoing::html(format!(
+                        "<b>{}</b> is not supported by YAAP!",
+                        html::escape(&device)
+                    )),
+                )
+                .await?;
+            return Ok(());
+        }
+        Err(e) => {
+            log::warn!("YAAP branch lookup for {} failed: {:?}", device, e);
+            app.transport
+                .reply(
+                    &message,
+                    format!(
+                        "Failed to get YAAP release information! (OTA Branch: {})",
+                        e
+                    )
+                    .into(),
+                )
                 .await?;
             return Ok(());
         }
     }
 
-    let gapps = format!(
-        "https://raw.githubusercontent.com/YAAP/ota-info/{}/{}/{}.json",
-        gapps_branch, device, device
+    let gapps = req::with_path(
+        "https://raw.githubusercontent.com/YAAP/ota-info",
+        &[gapps_branch.as_str(), device.as_str(), file.as_str()],
     );
-    let gapps_resp = plugins::req::make_request(gapps.to_string()).await;
+    let gapps_resp = app.http.get_json::<Value>(&gapps).await;
 
-    let vanilla = format!(
-        "https://raw.githubusercontent.com/YAAP/ota-info/{}/{}/{}.json",
-        vanilla_branch, device, device
+    let vanilla = req::with_path(
+        "https://raw.githubusercontent.com/YAAP/ota-info",
+        &[vanilla_branch.as_str(), device.as_str(), file.as_str()],
     );
-    let vanilla_resp = plugins::req::make_request(vanilla.to_string()).await;
+    let vanilla_resp = app.http.get_json::<Value>(&vanilla).await;
 
     let gapps_link;
 
@@ -94,7 +148,7 @@ pub async fn knightcmd_yaap(client: Client, message: Message, device: String) ->
     let msg;
 
     match gapps_resp {
-        Some(gapps_resp) => {
+        Ok(gapps_resp) => {
             gapps_link = gapps_resp["response"]
                 .as_array()
                 .and_then(|arr| arr.first())
@@ -103,17 +157,19 @@ pub async fn knightcmd_yaap(client: Client, message: Message, device: String) ->
                 .unwrap_or("Unknown filename")
                 .to_string();
         }
-        None => {
-            message
-                .reply(InputMessage::html(
-                    "Failed to get YAAP release information! (Gapps)",
-                ))
+        Err(e) => {
+            log::warn!("YAAP gapps lookup for {} failed: {:?}", device, e);
+            app.transport
+                .reply(
+                    &message,
+                    format!("Failed to get YAAP release information! (Gapps: {})", e).into(),
+                )
                 .await?;
             return Ok(());
         }
     }
     match vanilla_resp {
-        Some(vanilla_resp) => {
+        Ok(vanilla_resp) => {
             vanilla_link = vanilla_resp["response"]
                 .as_array()
                 .and_then(|arr| arr.first())
@@ -122,11 +178,13 @@ pub async fn knightcmd_yaap(client: Client, message: Message, device: String) ->
                 .unwrap_or("Unknown filename")
                 .to_string();
         }
-        None => {
-            message
-                .reply(InputMessage::html(
-                    "Failed to get YAAP release information! (Vanilla)",
-                ))
+        Err(e) => {
+            log::warn!("YAAP vanilla lookup for {} failed: {:?}", device, e);
+            app.transport
+                .reply(
+                    &message,
+                    format!("Failed to get YAAP release information! (Vanilla: {})", e).into(),
+                )
                 .await?;
             return Ok(());
         }
@@ -136,12 +194,13 @@ pub async fn knightcmd_yaap(client: Client, message: Message, device: String) ->
         .or_else(|| get_date(&vanilla_link))
         .unwrap_or("Unknown".to_string());
 
-    msg = InputMessage::html(format!(
+    msg = Outgoing::html(format!(
         "<b>Latest YAAP Releases for {} ({})</b>:",
-        device, date
+        html::escape(&device),
+        date
     ))
-    .reply_markup(&reply_markup::inline(vec![
-        vec![button::url(
+    .buttons(vec![
+        vec![Button::url(
             "Gapps",
             format!(
                 "https://mirror.codebucket.de/yaap/{}/{}",
@@ -149,7 +208,7 @@ pub async fn knightcmd_yaap(client: Client, message: Message, device: String) ->
                 gapps_link.to_string().trim_matches('"').to_string()
             ),
         )],
-        vec![button::url(
+        vec![Button::url(
             "Vanilla",
             format!(
                 "https://mirror.codebucket.de/yaap/{}/vanilla/{}",
@@ -157,14 +216,35 @@ pub async fn knightcmd_yaap(client: Client, message: Message, device: String) ->
                 vanilla_link.to_string().trim_matches('"').to_string()
             ),
         )],
-    ]));
+    ]);
 
     if let Some(id) = message.reply_to_message_id() {
-        client
-            .send_message(message.chat(), msg.reply_to(Some(id)))
+        app.transport
+            .send(message.chat(), msg.reply_to(Some(id)))
             .await?;
     } else {
-        message.reply(msg).await?;
+        app.transport.reply(&message, msg).await?;
     }
     return Ok(());
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, TestBot};
+
+    #[tokio::test]
+    async fn only_looks_up_codenames() {
+        let bot = TestBot::new();
+        for device in ["", "../../YAAP", "lemon?x"] {
+            let message = testing::message(1, "/yaap");
+            knightcmd_yaap(bot.app(), message, device.to_owned())
+                .await
+                .unwrap();
+            assert_eq!(
+                bot.take_sent(),
+                Outgoing::html("Provide a device <b>codename</b>!").reply_to(Some(1))
+            );
+        }
+    }
+}
@@ -0,0 +1,73 @@
+//!
+//! Copyright (C) 2023-2025 cyberknight777
+//!
+//! SPDX-License-Identifier: MIT
+//!
+
+/// Longest prefix accepted, anything longer is more likely a typo.
+const MAX_LENGTH: usize = 8;
+
+/// Checks that `prefix` can be told apart from the command following it.
+pub fn check(prefix: &str) -> Result<(), String> {
+    if prefix.is_empty() {
+        Err("prefixes must not be empty".to_owned())
+    } else if prefix.chars().count() > MAX_LENGTH {
+        Err(format!(
+            "prefix `{}` is longer than {} characters",
+            prefix, MAX_LENGTH
+        ))
+    } else if prefix.chars().any(|c| c.is_whitespace() || c == '@') {
+        Err(format!(
+            "prefix `{}` must not contain whitespace or `@`",
+            prefix
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Splits `cmd` into the prefix it starts with and the rest.
+///
+/// The admin prefix wins over `prefixes`, and among those the longest match
+/// wins, so `!!` and `!` can be used side by side.
+pub fn split<'a>(
+    cmd: &'a str,
+    admin: &str,
+    prefixes: &[String],
+) -> Option<(&'a str, &'a str, bool)> {
+    if let Some(name) = cmd.strip_prefix(admin) {
+        return Some((&cmd[..admin.len()], name, true));
+    }
+    prefixes
+        .iter()
+        .filter(|prefix| cmd.starts_with(prefix.as_str()))
+        .max_by_key(|prefix| prefix.len())
+        .map(|prefix| (&cmd[..prefix.len()], &cmd[prefix.len()..], false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prefixes(list: &[&str]) -> Vec<String> {
+        list.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn split_prefers_admin_and_longest_prefix() {
+        let list = prefixes(&["/", "!", "!!"]);
+        assert_eq!(split("k.sh", "k.", &list), Some(("k.", "sh", true)));
+        assert_eq!(split("/help", "k.", &list), Some(("/", "help", false)));
+        assert_eq!(split("!!ping", "k.", &list), Some(("!!", "ping", false)));
+        assert_eq!(split(".help", "k.", &list), None);
+    }
+
+    #[test]
+    fn check_rejects_ambiguous_prefixes() {
+        assert!(check("!").is_ok());
+        assert!(check("").is_err());
+        assert!(check("a b").is_err());
+        assert!(check("@").is_err());
+        assert!(check("toolongprefix").is_err());
+    }
+}
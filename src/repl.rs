@@ -0,0 +1,93 @@
+//!
+//! Copyright (C) 2023-2025 cyberknight777
+//!
+//! SPDX-License-Identifier: MIT
+//!
+
+use crate::transport::{console::Console, Incoming, Peer, PeerKind};
+use crate::{app::AppContext, cfg, plugins, store::Store, VERSION};
+use std::io::Write;
+use std::{path::PathBuf, sync::Arc};
+use tokio::io::{self, AsyncBufReadExt, BufReader};
+
+type Result = std::result::Result<(), Box<dyn std::error::Error>>;
+
+/// Who the bot is in the REPL; there is no account to ask.
+const BOT_ID: i64 = 1;
+const BOT_USERNAME: &str = "knight_bot";
+
+/// Reads commands from stdin and runs them through the same dispatcher as
+/// messages from Telegram, printing replies instead of sending them.
+///
+/// Whoever types is the owner, in a private chat with the bot. Settings
+/// changed from the REPL are not saved.
+pub async fn run(config_path: PathBuf) -> Result {
+    let config = Arc::new(cfg::Config::read_offline(&config_path)?);
+    let user = Peer::new(config.admin_id, "You", PeerKind::User);
+    let bot = Peer::new(BOT_ID, "Knight-Bot", PeerKind::User);
+    let console = Arc::new(Console::new(bot, user.clone()));
+    let app = Arc::new(
+        AppContext::new(
+            config_path,
+            config,
+            console.clone(),
+            Store::in_memory(),
+            BOT_ID,
+            BOT_USERNAME,
+        )?
+        .offline(),
+    );
+
+    println!(
+        "Knight-Bot v{} offline. Type commands such as /help, start a line with >N to reply \
+         to message #N, Ctrl-D quits.",
+        VERSION
+    );
+    let mut lines = BufReader::new(io::stdin()).lines();
+    loop {
+        let id = console.next_id();
+        print!("#{}> ", id);
+        std::io::stdout().flush()?;
+        let Some(line) = lines.next_line().await? else {
+            println!();
+            break;
+        };
+        let (reply_to, text) = split_reply(&line);
+        if text.is_empty() {
+            continue;
+        }
+        let message = Incoming::new(id, console.chat().clone(), Some(user.clone()), text)
+            .in_reply_to(reply_to);
+        console.remember(message.clone());
+        if let Err(e) = plugins::handle_msg(app.clone(), message).await {
+            eprintln!("Error handling the command: {}", e);
+        }
+    }
+    app.http.cache().persist().await;
+    Ok(())
+}
+
+/// Splits a leading `>N` off `line`, the message it replies to.
+fn split_reply(line: &str) -> (Option<i32>, &str) {
+    let line = line.trim();
+    if let Some(rest) = line.strip_prefix('>') {
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        if let Ok(id) = rest[..end].trim_start_matches('#').parse() {
+            return (Some(id), rest[end..].trim_start());
+        }
+    }
+    (None, line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reply_prefix_is_split_off() {
+        assert_eq!(split_reply("/help"), (None, "/help"));
+        assert_eq!(split_reply(">3 /paste"), (Some(3), "/paste"));
+        assert_eq!(split_reply(" >#12  /uid "), (Some(12), "/uid"));
+        assert_eq!(split_reply(">x /uid"), (None, ">x /uid"));
+    }
+}
@@ -0,0 +1,296 @@
+//!
+//! Copyright (C) 2023-2025 cyberknight777
+//!
+//! SPDX-License-Identifier: MIT
+//!
+
+use crate::app::AppContext;
+use crate::plugins::{
+    error::BotError,
+    html::{self, Html},
+};
+use crate::transport::{telegram::bot_api_peer, Incoming, Outgoing, Peer};
+use crate::VERSION;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Longest command, error line and backtrace posted, Telegram messages are
+/// capped at 4096 characters.
+const MAX_COMMAND: usize = 300;
+const MAX_CHAIN_LINE: usize = 300;
+const MAX_BACKTRACE: usize = 1500;
+/// Longest report as a whole, leaving some room for the markup Telegram
+/// counts differently.
+const MAX_REPORT: usize = 4000;
+
+struct Seen {
+    reported: Instant,
+    suppressed: u32,
+}
+
+struct Stats {
+    since: Instant,
+    commands: HashMap<&'static str, u64>,
+    errors: HashMap<&'static str, u64>,
+    suppressed: u64,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Stats {
+            since: Instant::now(),
+            commands: HashMap::new(),
+            errors: HashMap::new(),
+            suppressed: 0,
+        }
+    }
+}
+
+/// Posts errors, notices and daily summaries to `log_chat_id`.
+///
+/// Errors fall back to the owner's private chat if no log chat is set, and the
+/// same error from the same command is only posted once per dedup window.
+#[derive(Default)]
+pub struct Reporter {
+    seen: Mutex<HashMap<String, Seen>>,
+    stats: Mutex<Stats>,
+}
+
+impl Reporter {
+    /// Counts a command towards the daily summary.
+    pub fn command_ran(&self, name: &'static str) {
+        *self.stats.lock().unwrap().commands.entry(name).or_default() += 1;
+    }
+
+    /// Posts the details of a failed command.
+    pub async fn error(
+        &self,
+        app: &AppContext,
+        id: &str,
+        command: &str,
+        message: &Incoming,
+        error: &BotError,
+    ) {
+        let config = app.config();
+        let chat = bot_api_peer(config.log_chat_id.unwrap_or(config.admin_id));
+        if message.chat().id() == chat.id() {
+            return;
+        }
+
+        let chain = error.chain();
+        let window = Duration::from_secs(config.reporting.dedup_window_secs);
+        let suppressed = {
+            let mut stats = self.stats.lock().unwrap();
+            *stats.errors.entry(error.kind()).or_default() += 1;
+
+            let key = format!("{}: {}", command, chain[0]);
+            let now = Instant::now();
+            let mut seen = self.seen.lock().unwrap();
+            // Keep suppressed errors around a little longer so the next
+            // report can tell how many were left out.
+            let keep = window.max(Duration::from_secs(86400));
+            seen.retain(|_, seen| now.duration_since(seen.reported) < keep);
+            match seen.get_mut(&key) {
+                Some(seen) if now.duration_since(seen.reported) < window => {
+                    seen.suppressed += 1;
+                    stats.suppressed += 1;
+                    log::debug!("Not reporting error {}, already reported as {}", id, key);
+                    return;
+                }
+                Some(seen) => {
+                    let suppressed = seen.suppressed;
+                    *seen = Seen {
+                        reported: now,
+                        suppressed: 0,
+                    };
+                    suppressed
+                }
+                None => {
+                    seen.insert(
+                        key,
+                        Seen {
+                            reported: now,
+                            suppressed: 0,
+                        },
+                    );
+                    0
+                }
+            }
+        };
+
+        let sender = message.sender();
+        let mut text = Html::new()
+            .bold(format!("Error {}", id))
+            .line()
+            .field("Command", truncate(message.text(), MAX_COMMAND))
+            .field(
+                "Chat",
+                format!("{} ({})", message.chat().name(), message.chat().id()),
+            )
+            .field(
+                "User",
+                sender.map_or("unknown".to_owned(), |sender| {
+                    format!("{} ({})", sender.name(), sender.id())
+                }),
+            );
+        if suppressed > 0 {
+            text = text.field("Similar errors since last report", suppressed.to_string());
+        }
+        text = text.line();
+        for (depth, cause) in chain.iter().enumerate() {
+            let line = truncate(cause, MAX_CHAIN_LINE);
+            // Leave room for the `…` marking the causes left out.
+            if plain_len(&text) + "caused by: \n…\n".len() + line.len() > MAX_REPORT {
+                text = text.text("…").line();
+                break;
+            }
+            text = if depth == 0 {
+                text.code(line).line()
+            } else {
+                text.text("caused by: ").code(line).line()
+            };
+        }
+        if let Some(backtrace) = error.backtrace() {
+            let room = MAX_REPORT.saturating_sub(plain_len(&text) + 1);
+            if room > '…'.len_utf8() {
+                let backtrace = truncate(&backtrace.to_string(), MAX_BACKTRACE.min(room));
+                text = text.line().code(backtrace);
+            }
+        }
+        self.post(app, &chat, text).await;
+    }
+
+    /// Posts a one-line notice, e.g. on startup and shutdown.
+    pub async fn notice(&self, app: &AppContext, notice: &str) {
+        if let Some(chat) = app.config().log_chat_id {
+            let text = Html::new()
+                .bold(format!("Knight-Bot v{}", VERSION))
+                .text(": ")
+                .text(notice);
+            self.post(app, &bot_api_peer(chat), text).await;
+        }
+    }
+
+    /// Posts what happened since the last summary and starts counting anew.
+    pub async fn summary(&self, app: &AppContext) {
+        let stats = std::mem::take(&mut *self.stats.lock().unwrap());
+        let chat = match app.config().log_chat_id {
+            Some(chat) => chat,
+            None => return,
+        };
+
+        let mut commands = stats.commands.into_iter().collect::<Vec<_>>();
+        commands.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        let total = commands.iter().map(|(_, count)| count).sum::<u64>();
+        let mut errors = stats.errors.into_iter().collect::<Vec<_>>();
+        errors.sort();
+
+        let mut text = Html::new()
+            .bold(format!(
+                "Summary of the last {}h",
+                stats.since.elapsed().as_secs() / 3600
+            ))
+            .line()
+            .field("Commands", total.to_string());
+        for (name, count) in commands.iter().take(10) {
+            text = text.text(format!("  {}: {}", name, count)).line();
+        }
+        text = text.field(
+            "Errors",
+            errors
+                .iter()
+                .map(|(_, count)| count)
+                .sum::<u64>()
+                .to_string(),
+        );
+        for (kind, count) in &errors {
+            text = text.text(format!("  {}: {}", kind, count)).line();
+        }
+        if stats.suppressed > 0 {
+            text = text.field("Duplicate errors not posted", stats.suppressed.to_string());
+        }
+        self.post(app, &bot_api_peer(chat), text).await;
+    }
+
+    async fn post(&self, app: &AppContext, chat: &Peer, text: Html) {
+        let text = Outgoing::html(text.build());
+        if let Err(e) = app.transport.send(chat, text).await {
+            log::warn!("Failed to post to log chat {}: {}", chat.id(), e);
+        }
+    }
+}
+
+/// Length of `text` as Telegram sees it, in bytes, which is never less than
+/// what Telegram counts.
+fn plain_len(text: &Html) -> usize {
+    html::to_plain(&text.to_string()).len()
+}
+
+/// Cuts `text` down to `max` bytes, counting the `…` marking the cut.
+fn truncate(text: &str, max: usize) -> String {
+    if text.len() <= max {
+        return text.to_owned();
+    }
+    let mut at = max.saturating_sub('…'.len_utf8());
+    while !text.is_char_boundary(at) {
+        at -= 1;
+    }
+    format!("{}…", &text[..at])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfg::Config;
+    use crate::testing::{self, Event, FakeTransport};
+    use std::backtrace::Backtrace;
+    use std::{error::Error, fmt};
+
+    /// An error caused by `0` more of its kind, each with a long message.
+    #[derive(Debug)]
+    struct Layer(Option<Box<Layer>>);
+
+    impl fmt::Display for Layer {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str(&"x".repeat(1000))
+        }
+    }
+
+    impl Error for Layer {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            self.0.as_deref().map(|layer| layer as _)
+        }
+    }
+
+    #[test]
+    fn truncate_stays_within_max() {
+        assert_eq!(truncate("short", 10), "short");
+        assert_eq!(truncate("abcdefgh", 6), "abc…");
+        assert!(truncate(&"é".repeat(10), 7).len() <= 7);
+    }
+
+    #[tokio::test]
+    async fn long_reports_fit_in_a_message() {
+        let transport = FakeTransport::new();
+        let mut config = Config::default();
+        config.log_chat_id = Some(-1001234567890);
+        let app = testing::app_with(transport.clone(), config);
+
+        let error = (0..30).fold(Layer(None), |cause, _| Layer(Some(Box::new(cause))));
+        let error = BotError::Internal(Box::new(error), Backtrace::force_capture());
+        let message = testing::message(1, &format!("/msg {}", "y".repeat(5000)));
+        app.reporter
+            .error(&app, "abc123", "/msg", &message, &error)
+            .await;
+
+        let text = match transport.take().as_slice() {
+            [Event::Sent { outgoing, .. }] => outgoing.text.clone(),
+            events => panic!("expected a single report, got {:?}", events),
+        };
+        let plain = html::to_plain(&text);
+        assert!(plain.len() <= MAX_REPORT, "{} bytes", plain.len());
+        assert!(plain.starts_with("Error abc123\nCommand: /msg yyy"));
+        assert!(plain.contains("caused by: "));
+    }
+}
@@ -0,0 +1,391 @@
+//!
+//! Copyright (C) 2023-2025 cyberknight777
+//!
+//! SPDX-License-Identifier: MIT
+//!
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::{error, fmt, fs, io};
+use tokio::{sync::Mutex as AsyncMutex, task};
+
+/// Turns a document of version `n` into one of version `n + 1`.
+type Migration = fn(&mut Map<String, Value>);
+
+/// Every migration in order; the store's current version is their count.
+const MIGRATIONS: &[Migration] = &[];
+
+/// Settings of a single chat.
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq)]
+#[serde(default)]
+pub struct ChatSettings {
+    /// Command prefixes overriding `prefixes.commands`
+    pub prefixes: Option<Vec<String>>,
+    /// Commands turned off with `/disable`
+    pub disabled: BTreeSet<String>,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+struct Data {
+    version: usize,
+    chats: HashMap<i64, ChatSettings>,
+    /// Free-form values plugins keep under their own name
+    plugins: HashMap<String, BTreeMap<String, Value>>,
+}
+
+#[derive(Debug)]
+pub enum StoreError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    /// The file was written by a newer version of the bot.
+    TooNew(usize),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StoreError::Io(e) => write!(f, "{}", e),
+            StoreError::Json(e) => write!(f, "invalid store: {}", e),
+            StoreError::TooNew(version) => write!(
+                f,
+                "store is at version {}, this build only knows up to {}",
+                version,
+                MIGRATIONS.len()
+            ),
+        }
+    }
+}
+
+impl error::Error for StoreError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            StoreError::Io(e) => Some(e),
+            StoreError::Json(e) => Some(e),
+            StoreError::TooNew(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for StoreError {
+    fn from(e: io::Error) -> Self {
+        StoreError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for StoreError {
+    fn from(e: serde_json::Error) -> Self {
+        StoreError::Json(e)
+    }
+}
+
+/// Settings and plugin data that outlive a restart, kept in a JSON file.
+///
+/// Everything is held in memory and the whole file is rewritten on every
+/// change, which is plenty for the handful of chats a bot like this is in.
+/// Writing happens off the runtime thread, one change at a time, and a
+/// change that can't be saved is undone.
+pub struct Store {
+    /// File the store is saved to, `None` for one that only lives in memory
+    path: Option<PathBuf>,
+    data: Mutex<Data>,
+    /// Held from making a change until it is saved
+    writing: AsyncMutex<()>,
+}
+
+impl Store {
+    /// Opens the store at `path`, creating it if it does not exist and
+    /// migrating it if it was written by an older version.
+    ///
+    /// Unreadable stores are an error rather than being started over, so
+    /// nothing is lost to a typo made while editing the file by hand.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StoreError> {
+        let path = path.as_ref().to_owned();
+        let mut doc = read_doc(&path)?.unwrap_or_else(|| Value::Object(Map::new()));
+        let from = migrate(&mut doc, MIGRATIONS)?;
+        let store = Store::new(Some(path.clone()), serde_json::from_value(doc)?);
+        if from != MIGRATIONS.len() {
+            log::info!(
+                "Migrated store {} from version {} to {}",
+                path.display(),
+                from,
+                MIGRATIONS.len()
+            );
+            let json = serde_json::to_string_pretty(&*store.data.lock().unwrap())?;
+            write_file(&path, &json)?;
+        }
+        Ok(store)
+    }
+
+    /// An empty store that is never saved.
+    pub fn in_memory() -> Self {
+        Store::new(
+            None,
+            Data {
+                version: MIGRATIONS.len(),
+                ..Data::default()
+            },
+        )
+    }
+
+    fn new(path: Option<PathBuf>, data: Data) -> Self {
+        Store {
+            path,
+            data: Mutex::new(data),
+            writing: AsyncMutex::new(()),
+        }
+    }
+
+    /// Returns the settings of `chat`, the defaults if it has none.
+    pub fn chat(&self, chat: i64) -> ChatSettings {
+        let data = self.data.lock().unwrap();
+        data.chats.get(&chat).cloned().unwrap_or_default()
+    }
+
+    /// Changes the settings of `chat` and saves them.
+    pub async fn update_chat<R>(
+        &self,
+        chat: i64,
+        update: impl FnOnce(&mut ChatSettings) -> R,
+    ) -> Result<R, StoreError> {
+        self.update(|data| update_entry(&mut data.chats, chat, update))
+            .await
+    }
+
+    /// Data kept by the plugin called `name`, out of reach of other plugins.
+    pub fn plugin(&self, name: &'static str) -> PluginData<'_> {
+        PluginData { store: self, name }
+    }
+
+    /// Runs `update` and saves the result, putting everything back the way
+    /// it was if saving fails.
+    async fn update<R>(&self, update: impl FnOnce(&mut Data) -> R) -> Result<R, StoreError> {
+        // Changes are saved one after the other, so nothing else touches the
+        // data between taking the snapshot and restoring it.
+        let _writing = self.writing.lock().await;
+        let (result, json, before) = {
+            let mut data = self.data.lock().unwrap();
+            let before = data.clone();
+            let result = update(&mut data);
+            match serde_json::to_string_pretty(&*data) {
+                Ok(json) => (result, json, before),
+                Err(e) => {
+                    *data = before;
+                    return Err(e.into());
+                }
+            }
+        };
+        let Some(path) = self.path.clone() else {
+            return Ok(result);
+        };
+        let saved = task::spawn_blocking(move || write_file(&path, &json))
+            .await
+            .unwrap_or_else(|e| Err(io::Error::other(e)));
+        if let Err(e) = saved {
+            *self.data.lock().unwrap() = before;
+            return Err(e.into());
+        }
+        Ok(result)
+    }
+}
+
+/// Typed key-value access to the data of a single plugin.
+pub struct PluginData<'a> {
+    store: &'a Store,
+    name: &'static str,
+}
+
+impl PluginData<'_> {
+    /// Returns the value stored under `key`.
+    ///
+    /// Values that no longer fit `T` are treated as missing.
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let data = self.store.data.lock().unwrap();
+        let value = data.plugins.get(self.name)?.get(key)?;
+        match T::deserialize(value) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                log::warn!("Ignoring stored {}/{}: {}", self.name, key, e);
+                None
+            }
+        }
+    }
+
+    /// Stores `value` under `key`, replacing what was there.
+    pub async fn set<T: Serialize>(&self, key: &str, value: &T) -> Result<(), StoreError> {
+        let value = serde_json::to_value(value)?;
+        self.store
+            .update(|data| {
+                data.plugins
+                    .entry(self.name.to_owned())
+                    .or_default()
+                    .insert(key.to_owned(), value);
+            })
+            .await
+    }
+}
+
+/// Reads the JSON document at `path`, `None` if there is no such file.
+fn read_doc(path: &Path) -> Result<Option<Value>, StoreError> {
+    match fs::read_to_string(path) {
+        Ok(json) => Ok(Some(serde_json::from_str(&json)?)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Replaces the file at `path` with `json` without ever leaving half of it.
+fn write_file(path: &Path, json: &str) -> io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, json)?;
+    fs::rename(&tmp, path)
+}
+
+/// Runs `update` on the entry for `id`, dropping it again if it ends up
+/// with nothing but defaults so the file only holds what was changed.
+fn update_entry<T: Default + PartialEq, R>(
+    entries: &mut HashMap<i64, T>,
+    id: i64,
+    update: impl FnOnce(&mut T) -> R,
+) -> R {
+    let entry = entries.entry(id).or_default();
+    let result = update(entry);
+    if *entry == T::default() {
+        entries.remove(&id);
+    }
+    result
+}
+
+/// Brings `doc` up to the version after the last of `migrations`.
+///
+/// Returns the version it was at before.
+fn migrate(doc: &mut Value, migrations: &[Migration]) -> Result<usize, StoreError> {
+    let Value::Object(map) = doc else {
+        return Err(StoreError::Json(serde::de::Error::custom(
+            "expected an object at the top level",
+        )));
+    };
+    let from = match map.get("version") {
+        None => 0,
+        Some(version) => version
+            .as_u64()
+            .ok_or_else(|| StoreError::Json(serde::de::Error::custom("version must be a number")))?
+            as usize,
+    };
+    if from > migrations.len() {
+        return Err(StoreError::TooNew(from));
+    }
+    for (version, migration) in migrations.iter().enumerate().skip(from) {
+        migration(map);
+        map.insert("version".to_owned(), Value::from(version + 1));
+    }
+    Ok(from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// A fresh path under the temp dir, removed again when dropped.
+    struct TempPath(PathBuf);
+
+    impl TempPath {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "knight-bot-{}-{}.json",
+                name,
+                std::process::id()
+            ));
+            let _ = fs::remove_file(&path);
+            TempPath(path)
+        }
+    }
+
+    impl Drop for TempPath {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[tokio::test]
+    async fn settings_survive_reopening() {
+        let path = TempPath::new("reopen");
+        let store = Store::open(&path.0).unwrap();
+        store
+            .update_chat(-42, |chat| chat.prefixes = Some(vec!["!".to_owned()]))
+            .await
+            .unwrap();
+        store
+            .plugin("notes")
+            .set("rules", &"be nice")
+            .await
+            .unwrap();
+
+        let store = Store::open(&path.0).unwrap();
+        assert_eq!(store.chat(-42).prefixes, Some(vec!["!".to_owned()]));
+        assert!(store.chat(7).prefixes.is_none());
+        assert_eq!(
+            store.plugin("notes").get::<String>("rules").as_deref(),
+            Some("be nice")
+        );
+        assert_eq!(store.plugin("other").get::<String>("rules"), None);
+    }
+
+    #[tokio::test]
+    async fn defaults_are_not_kept() {
+        let path = TempPath::new("defaults");
+        let store = Store::open(&path.0).unwrap();
+        store
+            .update_chat(1, |chat| chat.prefixes = Some(vec![".".to_owned()]))
+            .await
+            .unwrap();
+        store
+            .update_chat(1, |chat| chat.prefixes = None)
+            .await
+            .unwrap();
+        assert!(store.data.lock().unwrap().chats.is_empty());
+    }
+
+    #[tokio::test]
+    async fn failed_saves_are_undone() {
+        let dir = std::env::temp_dir().join(format!("knight-bot-missing-{}", std::process::id()));
+        let store = Store::new(Some(dir.join("store.json")), Data::default());
+
+        let result = store
+            .update_chat(5, |chat| chat.disabled.insert("cat".to_owned()))
+            .await;
+        assert!(matches!(result, Err(StoreError::Io(_))));
+        assert!(store.chat(5).disabled.is_empty());
+
+        assert!(store.plugin("notes").set("a", &1).await.is_err());
+        assert_eq!(store.plugin("notes").get::<i32>("a"), None);
+    }
+
+    #[test]
+    fn migrations_run_from_the_stored_version() {
+        fn first(map: &mut Map<String, Value>) {
+            map.insert("first".to_owned(), Value::from(true));
+        }
+        fn second(map: &mut Map<String, Value>) {
+            map.insert("second".to_owned(), Value::from(true));
+        }
+
+        let mut doc = json!({});
+        assert_eq!(migrate(&mut doc, &[first, second]).unwrap(), 0);
+        assert_eq!(doc, json!({"version": 2, "first": true, "second": true}));
+
+        let mut doc = json!({"version": 1});
+        assert_eq!(migrate(&mut doc, &[first, second]).unwrap(), 1);
+        assert_eq!(doc, json!({"version": 2, "second": true}));
+
+        let mut doc = json!({"version": 3});
+        assert!(matches!(
+            migrate(&mut doc, &[first, second]),
+            Err(StoreError::TooNew(3))
+        ));
+    }
+}
@@ -0,0 +1,287 @@
+//!
+//! Copyright (C) 2023-2025 cyberknight777
+//!
+//! SPDX-License-Identifier: MIT
+//!
+
+//! Fakes for testing plugins offline: a transport recording what would have
+//! been sent, and an HTTP server answering with canned responses.
+
+use crate::app::AppContext;
+use crate::cfg::Config;
+use crate::store::Store;
+use crate::transport::{Fut, Incoming, Outgoing, Peer, PeerKind, Transport};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// ID the bot runs as in tests.
+pub const BOT_ID: i64 = 1;
+/// Group the test messages are sent in.
+pub const CHAT_ID: i64 = 100;
+/// User sending the test messages.
+pub const USER_ID: i64 = 42;
+
+/// Something plugins did through the [`FakeTransport`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum Event {
+    /// A new message in chat `chat`.
+    Sent { chat: i64, outgoing: Outgoing },
+    /// Message `id` was edited.
+    Edited { id: i32, outgoing: Outgoing },
+}
+
+/// A [`Transport`] that records everything instead of sending it.
+pub struct FakeTransport {
+    events: Mutex<Vec<Event>>,
+    next_id: AtomicI32,
+    /// Messages plugins can get at as the one replied to
+    messages: Mutex<HashMap<i32, Incoming>>,
+    /// Contents of documents attached to messages
+    media: Mutex<HashMap<i32, Vec<u8>>>,
+    chat_admins: Mutex<HashSet<i64>>,
+}
+
+impl FakeTransport {
+    pub fn new() -> Arc<Self> {
+        Arc::new(FakeTransport {
+            events: Mutex::new(Vec::new()),
+            next_id: AtomicI32::new(1000),
+            messages: Mutex::new(HashMap::new()),
+            media: Mutex::new(HashMap::new()),
+            chat_admins: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// Makes `message` available to [`Transport::reply_to_message`].
+    pub fn remember(&self, message: Incoming) {
+        self.messages.lock().unwrap().insert(message.id(), message);
+    }
+
+    /// Attaches `contents` to message `id` for [`Transport::download_media`].
+    pub fn attach(&self, id: i32, contents: impl Into<Vec<u8>>) {
+        self.media.lock().unwrap().insert(id, contents.into());
+    }
+
+    pub fn make_chat_admin(&self, user: i64) {
+        self.chat_admins.lock().unwrap().insert(user);
+    }
+
+    /// Takes everything recorded so far.
+    pub fn take(&self) -> Vec<Event> {
+        std::mem::take(&mut *self.events.lock().unwrap())
+    }
+
+    /// Takes everything recorded so far, expecting nothing but a single
+    /// message sent.
+    pub fn take_sent(&self) -> Outgoing {
+        match self.take().as_slice() {
+            [Event::Sent { outgoing, .. }] => outgoing.clone(),
+            events => panic!("expected a single message, got {:?}", events),
+        }
+    }
+
+    fn record(&self, event: Event) {
+        self.events.lock().unwrap().push(event);
+    }
+}
+
+impl Transport for FakeTransport {
+    fn reply<'a>(&'a self, message: &'a Incoming, outgoing: Outgoing) -> Fut<'a, Incoming> {
+        let reply_to = outgoing.reply_to.or(Some(message.id()));
+        self.send(message.chat(), outgoing.reply_to(reply_to))
+    }
+
+    fn send<'a>(&'a self, chat: &'a Peer, outgoing: Outgoing) -> Fut<'a, Incoming> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let sent = Incoming::new(id, chat.clone(), Some(bot()), outgoing.text.clone())
+            .in_reply_to(outgoing.reply_to);
+        self.record(Event::Sent {
+            chat: chat.id(),
+            outgoing,
+        });
+        Box::pin(async move { Ok(sent) })
+    }
+
+    fn edit<'a>(&'a self, message: &'a Incoming, outgoing: Outgoing) -> Fut<'a, ()> {
+        self.record(Event::Edited {
+            id: message.id(),
+            outgoing,
+        });
+        Box::pin(async { Ok(()) })
+    }
+
+    fn reply_to_message<'a>(&'a self, message: &'a Incoming) -> Fut<'a, Option<Incoming>> {
+        let reply = message
+            .reply_to_message_id()
+            .and_then(|id| self.messages.lock().unwrap().get(&id).cloned());
+        Box::pin(async move { Ok(reply) })
+    }
+
+    fn download_media<'a>(&'a self, message: &'a Incoming) -> Fut<'a, Option<Vec<u8>>> {
+        let contents = self.media.lock().unwrap().get(&message.id()).cloned();
+        Box::pin(async move { Ok(contents) })
+    }
+
+    fn is_chat_admin<'a>(&'a self, _chat: &'a Peer, user: &'a Peer) -> Fut<'a, bool> {
+        let admin = self.chat_admins.lock().unwrap().contains(&user.id());
+        Box::pin(async move { Ok(admin) })
+    }
+}
+
+pub fn bot() -> Peer {
+    Peer::new(BOT_ID, "Knight-Bot", PeerKind::User)
+}
+
+pub fn user(id: i64) -> Peer {
+    Peer::new(id, format!("User {}", id), PeerKind::User)
+}
+
+pub fn group() -> Peer {
+    Peer::new(CHAT_ID, "Test group", PeerKind::Group)
+}
+
+/// Message `id` sent by [`USER_ID`] in the test group.
+pub fn message(id: i32, text: &str) -> Incoming {
+    Incoming::new(id, group(), Some(user(USER_ID)), text)
+}
+
+/// An app sending through `transport`, with the default config and a store
+/// that only lives in memory.
+pub fn app(transport: Arc<FakeTransport>) -> AppContext {
+    app_with(transport, Config::default())
+}
+
+pub fn app_with(transport: Arc<FakeTransport>, config: Config) -> AppContext {
+    AppContext::new(
+        PathBuf::from("config.toml"),
+        Arc::new(config),
+        transport,
+        Store::in_memory(),
+        BOT_ID,
+        "knight_bot",
+    )
+    .unwrap()
+}
+
+/// An app and the [`FakeTransport`] it sends through, for running a plugin
+/// and checking what it sent.
+pub struct TestBot {
+    pub app: Arc<AppContext>,
+    pub transport: Arc<FakeTransport>,
+}
+
+impl Default for TestBot {
+    /// A bot with the default config, see [`app`].
+    fn default() -> Self {
+        TestBot::with_config(Config::default())
+    }
+}
+
+impl TestBot {
+    pub fn new() -> Self {
+        TestBot::default()
+    }
+
+    pub fn with_config(config: Config) -> Self {
+        let transport = FakeTransport::new();
+        TestBot {
+            app: Arc::new(app_with(transport.clone(), config)),
+            transport,
+        }
+    }
+
+    /// Sends requests for URLs starting with `from` to `stub` instead.
+    pub fn redirect(mut self, from: &str, stub: &StubServer) -> Self {
+        Arc::get_mut(&mut self.app)
+            .expect("redirect before handing out the app")
+            .http
+            .redirect(from, stub.url());
+        self
+    }
+
+    /// The app, to hand to a plugin.
+    pub fn app(&self) -> Arc<AppContext> {
+        self.app.clone()
+    }
+
+    /// See [`FakeTransport::take`].
+    pub fn take(&self) -> Vec<Event> {
+        self.transport.take()
+    }
+
+    /// See [`FakeTransport::take_sent`].
+    pub fn take_sent(&self) -> Outgoing {
+        self.transport.take_sent()
+    }
+}
+
+/// A local HTTP server answering every request for a known path and query
+/// with a canned response, and with `404` otherwise.
+pub struct StubServer {
+    url: String,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl StubServer {
+    /// Starts serving `routes`, each a path with its query, the status to
+    /// answer with and the body.
+    pub async fn start(routes: &[(&str, u16, &str)]) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let routes = routes
+            .iter()
+            .map(|(path, status, body)| (path.to_string(), (*status, body.to_string())))
+            .collect::<HashMap<_, _>>();
+        let routes = Arc::new(routes);
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let log = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let routes = routes.clone();
+                let log = log.clone();
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buffer = [0; 1024];
+                    while !request.ends_with(b"\r\n\r\n") {
+                        match stream.read(&mut buffer).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => request.extend_from_slice(&buffer[..n]),
+                        }
+                    }
+                    let request = String::from_utf8_lossy(&request);
+                    let path = request.split(' ').nth(1).unwrap_or_default().to_owned();
+                    let (status, body) = routes
+                        .get(&path)
+                        .cloned()
+                        .unwrap_or((404, "not found".to_owned()));
+                    log.lock().unwrap().push(path);
+                    let response = format!(
+                        "HTTP/1.1 {} Stub\r\nContent-Type: application/json\r\n\
+                         Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        status,
+                        body.len(),
+                        body
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+
+        StubServer { url, requests }
+    }
+
+    /// Base URL to redirect requests to, e.g. `http://127.0.0.1:1234`.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Paths requested so far, in order.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
@@ -0,0 +1,143 @@
+//!
+//! Copyright (C) 2023-2025 cyberknight777
+//!
+//! SPDX-License-Identifier: MIT
+//!
+
+use crate::plugins::html;
+use crate::transport::{Format, Fut, Incoming, Outgoing, Peer, Transport};
+use std::collections::HashMap;
+use std::fs;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::Mutex;
+
+/// Prints what would have been sent to Telegram, for `--repl`.
+pub struct Console {
+    next_id: AtomicI32,
+    /// Every message typed or printed, so they can be replied to
+    messages: Mutex<HashMap<i32, Incoming>>,
+    bot: Peer,
+    /// The chat commands are typed in
+    chat: Peer,
+}
+
+impl Console {
+    /// A console printing messages as sent by `bot` in `chat`.
+    pub fn new(bot: Peer, chat: Peer) -> Self {
+        Console {
+            next_id: AtomicI32::new(1),
+            messages: Mutex::new(HashMap::new()),
+            bot,
+            chat,
+        }
+    }
+
+    pub fn chat(&self) -> &Peer {
+        &self.chat
+    }
+
+    /// ID for the next message, typed or printed.
+    pub fn next_id(&self) -> i32 {
+        self.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Keeps `message` around for [`Transport::reply_to_message`].
+    pub fn remember(&self, message: Incoming) {
+        self.messages.lock().unwrap().insert(message.id(), message);
+    }
+
+    fn print(&self, header: String, id: i32, outgoing: &Outgoing) {
+        let mut out = format!("── {} ──\n", header);
+        let text = match outgoing.format {
+            Format::Html => html::to_plain(&outgoing.text),
+            Format::Plain | Format::Markdown => outgoing.text.clone(),
+        };
+        if !text.is_empty() {
+            out.push_str(text.trim_end());
+            out.push('\n');
+        }
+        if let Some(url) = &outgoing.photo_url {
+            out.push_str(&format!("[photo] {}\n", url));
+        }
+        if let Some((name, contents)) = &outgoing.document {
+            let path = std::env::temp_dir().join(format!("knight-bot-{}-{}", id, name));
+            match fs::write(&path, contents) {
+                Ok(()) => out.push_str(&format!(
+                    "[document] {} ({} bytes), saved to {}\n",
+                    name,
+                    contents.len(),
+                    path.display()
+                )),
+                Err(e) => out.push_str(&format!(
+                    "[document] {} ({} bytes), not saved: {}\n",
+                    name,
+                    contents.len(),
+                    e
+                )),
+            }
+        }
+        for row in &outgoing.buttons {
+            let row = row
+                .iter()
+                .map(|button| format!("[ {} ]({})", button.text, button.url))
+                .collect::<Vec<_>>();
+            out.push_str(&row.join("  "));
+            out.push('\n');
+        }
+        print!("{}", out);
+    }
+}
+
+impl Transport for Console {
+    fn reply<'a>(&'a self, message: &'a Incoming, outgoing: Outgoing) -> Fut<'a, Incoming> {
+        let reply_to = outgoing.reply_to.or(Some(message.id()));
+        self.send(message.chat(), outgoing.reply_to(reply_to))
+    }
+
+    fn send<'a>(&'a self, chat: &'a Peer, outgoing: Outgoing) -> Fut<'a, Incoming> {
+        let id = self.next_id();
+        let mut header = format!("#{}", id);
+        if let Some(reply_to) = outgoing.reply_to {
+            header.push_str(&format!(", reply to #{}", reply_to));
+        }
+        // Such as error reports for the log chat.
+        if chat.id() != self.chat.id() {
+            header.push_str(&format!(", to chat {}", chat.id()));
+        }
+        self.print(header, id, &outgoing);
+        let sent = Incoming::new(id, chat.clone(), Some(self.bot.clone()), outgoing.text)
+            .in_reply_to(outgoing.reply_to);
+        self.remember(sent.clone());
+        Box::pin(async move { Ok(sent) })
+    }
+
+    fn edit<'a>(&'a self, message: &'a Incoming, outgoing: Outgoing) -> Fut<'a, ()> {
+        self.print(format!("#{} edited", message.id()), message.id(), &outgoing);
+        let edited = Incoming::new(
+            message.id(),
+            message.chat().clone(),
+            message.sender().cloned(),
+            outgoing.text,
+        )
+        .in_reply_to(message.reply_to_message_id());
+        self.remember(edited);
+        Box::pin(async { Ok(()) })
+    }
+
+    fn reply_to_message<'a>(&'a self, message: &'a Incoming) -> Fut<'a, Option<Incoming>> {
+        let reply = message
+            .reply_to_message_id()
+            .and_then(|id| self.messages.lock().unwrap().get(&id).cloned());
+        Box::pin(async move { Ok(reply) })
+    }
+
+    fn download_media<'a>(&'a self, _message: &'a Incoming) -> Fut<'a, Option<Vec<u8>>> {
+        // Nothing typed in a terminal has media attached.
+        Box::pin(async { Ok(None) })
+    }
+
+    fn is_chat_admin<'a>(&'a self, _chat: &'a Peer, _user: &'a Peer) -> Fut<'a, bool> {
+        // Whoever types has the chat to themselves.
+        Box::pin(async { Ok(true) })
+    }
+}
@@ -0,0 +1,310 @@
+//!
+//! Copyright (C) 2023-2025 cyberknight777
+//!
+//! SPDX-License-Identifier: MIT
+//!
+
+use crate::outbox::OutboxStats;
+use grammers_client::{Client, InvocationError};
+use grammers_session::PackedChat;
+use std::future::Future;
+use std::pin::Pin;
+use std::{error, fmt, io};
+
+pub mod console;
+pub mod telegram;
+
+/// Future returned by [`Transport`] methods.
+pub type Fut<'a, T> = Pin<Box<dyn Future<Output = Result<T, TransportError>> + Send + 'a>>;
+
+#[derive(Debug)]
+pub enum TransportError {
+    Telegram(InvocationError),
+    Io(io::Error),
+    /// The chat was never seen, so there is no way to address it.
+    UnknownChat(i64),
+}
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TransportError::Telegram(e) => write!(f, "{}", e),
+            TransportError::Io(e) => write!(f, "{}", e),
+            TransportError::UnknownChat(id) => write!(f, "chat {} can't be addressed", id),
+        }
+    }
+}
+
+impl error::Error for TransportError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            TransportError::Telegram(e) => Some(e),
+            TransportError::Io(e) => Some(e),
+            TransportError::UnknownChat(_) => None,
+        }
+    }
+}
+
+impl From<InvocationError> for TransportError {
+    fn from(e: InvocationError) -> Self {
+        TransportError::Telegram(e)
+    }
+}
+
+impl From<io::Error> for TransportError {
+    fn from(e: io::Error) -> Self {
+        TransportError::Io(e)
+    }
+}
+
+/// Everything plugins do to talk back to the chat they were invoked in.
+///
+/// Telegram is the one real implementation. `--repl` prints what would have
+/// been sent and the tests record it, so plugins can be tried and checked
+/// without a connection.
+pub trait Transport: Send + Sync {
+    /// Sends `outgoing` to the chat of `message`, replying to it unless
+    /// `outgoing` replies to something else.
+    fn reply<'a>(&'a self, message: &'a Incoming, outgoing: Outgoing) -> Fut<'a, Incoming>;
+
+    /// Sends `outgoing` to `chat`.
+    fn send<'a>(&'a self, chat: &'a Peer, outgoing: Outgoing) -> Fut<'a, Incoming>;
+
+    /// Replaces the contents of one of our own messages.
+    fn edit<'a>(&'a self, message: &'a Incoming, outgoing: Outgoing) -> Fut<'a, ()>;
+
+    /// Like [`Transport::edit`], but without waiting for the chat's turn or
+    /// sitting out flood waits, for updates a fresher one soon replaces.
+    fn edit_now<'a>(&'a self, message: &'a Incoming, outgoing: Outgoing) -> Fut<'a, ()> {
+        self.edit(message, outgoing)
+    }
+
+    /// The message `message` replies to, if any and if it still exists.
+    fn reply_to_message<'a>(&'a self, message: &'a Incoming) -> Fut<'a, Option<Incoming>>;
+
+    /// Contents of the document attached to `message`, if any.
+    fn download_media<'a>(&'a self, message: &'a Incoming) -> Fut<'a, Option<Vec<u8>>>;
+
+    /// Whether `user` administers `chat`.
+    fn is_chat_admin<'a>(&'a self, chat: &'a Peer, user: &'a Peer) -> Fut<'a, bool>;
+
+    /// Counters of the queue messages wait in, if there is one.
+    fn stats(&self) -> Option<OutboxStats> {
+        None
+    }
+
+    /// The Telegram client, for the few things only Telegram can do.
+    fn client(&self) -> Option<Client> {
+        None
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PeerKind {
+    User,
+    Group,
+    Channel,
+}
+
+/// A chat, or the user or chat a message was sent by.
+#[derive(Clone, Debug)]
+pub struct Peer {
+    id: i64,
+    name: String,
+    kind: PeerKind,
+    /// How Telegram addresses the chat, absent for chats it doesn't know.
+    packed: Option<PackedChat>,
+}
+
+impl Peer {
+    pub fn new(id: i64, name: impl Into<String>, kind: PeerKind) -> Self {
+        Peer {
+            id,
+            name: name.into(),
+            kind,
+            packed: None,
+        }
+    }
+
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn kind(&self) -> PeerKind {
+        self.kind
+    }
+
+    pub fn packed(&self) -> Option<PackedChat> {
+        self.packed
+    }
+
+    pub fn with_packed(mut self, packed: PackedChat) -> Self {
+        self.packed = Some(packed);
+        self
+    }
+}
+
+/// A document attached to a message.
+#[derive(Clone, Debug)]
+pub struct Document {
+    pub name: String,
+    pub size: i64,
+}
+
+/// A message as plugins see it.
+#[derive(Clone, Debug)]
+pub struct Incoming {
+    id: i32,
+    chat: Peer,
+    sender: Option<Peer>,
+    text: String,
+    reply_to: Option<i32>,
+    document: Option<Document>,
+}
+
+impl Incoming {
+    pub fn new(id: i32, chat: Peer, sender: Option<Peer>, text: impl Into<String>) -> Self {
+        Incoming {
+            id,
+            chat,
+            sender,
+            text: text.into(),
+            reply_to: None,
+            document: None,
+        }
+    }
+
+    /// Marks the message as a reply to message `id`.
+    pub fn in_reply_to(mut self, id: Option<i32>) -> Self {
+        self.reply_to = id;
+        self
+    }
+
+    pub fn with_document(mut self, document: Document) -> Self {
+        self.document = Some(document);
+        self
+    }
+
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    pub fn chat(&self) -> &Peer {
+        &self.chat
+    }
+
+    pub fn sender(&self) -> Option<&Peer> {
+        self.sender.as_ref()
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn reply_to_message_id(&self) -> Option<i32> {
+        self.reply_to
+    }
+
+    pub fn document(&self) -> Option<&Document> {
+        self.document.as_ref()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Plain,
+    Html,
+    Markdown,
+}
+
+/// A button below a message, opening `url`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Button {
+    pub text: String,
+    pub url: String,
+}
+
+impl Button {
+    pub fn url(text: impl Into<String>, url: impl Into<String>) -> Self {
+        Button {
+            text: text.into(),
+            url: url.into(),
+        }
+    }
+}
+
+/// A message to send, or the new contents of one being edited.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Outgoing {
+    pub text: String,
+    pub format: Format,
+    pub reply_to: Option<i32>,
+    pub photo_url: Option<String>,
+    /// File name and contents of a document to upload
+    pub document: Option<(String, Vec<u8>)>,
+    pub buttons: Vec<Vec<Button>>,
+}
+
+impl Outgoing {
+    pub fn text(text: impl Into<String>) -> Self {
+        Outgoing {
+            text: text.into(),
+            format: Format::Plain,
+            reply_to: None,
+            photo_url: None,
+            document: None,
+            buttons: Vec::new(),
+        }
+    }
+
+    pub fn html(text: impl Into<String>) -> Self {
+        Outgoing {
+            format: Format::Html,
+            ..Outgoing::text(text)
+        }
+    }
+
+    pub fn markdown(text: impl Into<String>) -> Self {
+        Outgoing {
+            format: Format::Markdown,
+            ..Outgoing::text(text)
+        }
+    }
+
+    pub fn reply_to(mut self, id: Option<i32>) -> Self {
+        self.reply_to = id;
+        self
+    }
+
+    pub fn photo_url(mut self, url: impl Into<String>) -> Self {
+        self.photo_url = Some(url.into());
+        self
+    }
+
+    pub fn document(mut self, name: impl Into<String>, contents: Vec<u8>) -> Self {
+        self.document = Some((name.into(), contents));
+        self
+    }
+
+    /// Rows of buttons shown below the message.
+    pub fn buttons(mut self, buttons: Vec<Vec<Button>>) -> Self {
+        self.buttons = buttons;
+        self
+    }
+}
+
+impl From<&str> for Outgoing {
+    fn from(text: &str) -> Self {
+        Outgoing::text(text)
+    }
+}
+
+impl From<String> for Outgoing {
+    fn from(text: String) -> Self {
+        Outgoing::text(text)
+    }
+}
@@ -0,0 +1,339 @@
+//!
+//! Copyright (C) 2023-2025 cyberknight777
+//!
+//! SPDX-License-Identifier: MIT
+//!
+
+use crate::app::AppContext;
+use crate::outbox::{Outbox, OutboxStats};
+use crate::plugins::inline::{self, InlineResult};
+use crate::transport::{
+    Document, Format, Fut, Incoming, Outgoing, Peer, PeerKind, Transport, TransportError,
+};
+use grammers_client::{
+    button, parsers, reply_markup,
+    types::{Chat, InlineQuery, InputMessage, Media, Message},
+    Client,
+};
+use grammers_session::{PackedChat, PackedType};
+use grammers_tl_types as tl;
+use std::io::Cursor;
+use std::sync::{Arc, RwLock};
+
+/// Talks to Telegram, sending through an [`Outbox`] so flood waits are sat out.
+pub struct Telegram {
+    client: RwLock<Client>,
+    outbox: Outbox,
+}
+
+impl Telegram {
+    pub fn new(client: Client, outbox: Outbox) -> Self {
+        Telegram {
+            client: RwLock::new(client),
+            outbox,
+        }
+    }
+
+    /// Sends through `client` from now on, after a reconnect replaced it.
+    pub fn reconnected(&self, client: Client) {
+        *self.client.write().unwrap() = client;
+    }
+
+    fn current(&self) -> Client {
+        self.client.read().unwrap().clone()
+    }
+
+    /// Turns `outgoing` into something grammers can send, uploading its
+    /// document first if it has one.
+    async fn input(&self, outgoing: Outgoing) -> Result<InputMessage, TransportError> {
+        let mut input = match outgoing.format {
+            Format::Plain => InputMessage::text(outgoing.text),
+            Format::Html => InputMessage::html(outgoing.text),
+            Format::Markdown => InputMessage::markdown(outgoing.text),
+        }
+        .reply_to(outgoing.reply_to);
+        if let Some(url) = outgoing.photo_url {
+            input = input.photo_url(url);
+        }
+        if let Some((name, contents)) = outgoing.document {
+            let size = contents.len();
+            let mut stream = Cursor::new(contents);
+            let file = self
+                .current()
+                .upload_stream(&mut stream, size, name)
+                .await?;
+            input = input.document(file);
+        }
+        if !outgoing.buttons.is_empty() {
+            let rows = outgoing
+                .buttons
+                .into_iter()
+                .map(|row| {
+                    row.into_iter()
+                        .map(|b| button::url(b.text, b.url))
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            input = input.reply_markup(&reply_markup::inline(rows));
+        }
+        Ok(input)
+    }
+
+    /// Fetches message `id` of `chat` anew, e.g. to get at its media.
+    async fn fetch(&self, chat: &Peer, id: i32) -> Result<Option<Message>, TransportError> {
+        let messages = self
+            .current()
+            .get_messages_by_id(packed(chat)?, &[id])
+            .await?;
+        Ok(messages.into_iter().next().flatten())
+    }
+}
+
+impl Transport for Telegram {
+    fn reply<'a>(&'a self, message: &'a Incoming, outgoing: Outgoing) -> Fut<'a, Incoming> {
+        let reply_to = outgoing.reply_to.or(Some(message.id()));
+        self.send(message.chat(), outgoing.reply_to(reply_to))
+    }
+
+    fn send<'a>(&'a self, chat: &'a Peer, outgoing: Outgoing) -> Fut<'a, Incoming> {
+        Box::pin(async move {
+            let packed = packed(chat)?;
+            let input = self.input(outgoing).await?;
+            let client = self.current();
+            let sent = self
+                .outbox
+                .queued(chat.id(), || client.send_message(packed, input.clone()))
+                .await?;
+            Ok(incoming(&sent))
+        })
+    }
+
+    fn edit<'a>(&'a self, message: &'a Incoming, outgoing: Outgoing) -> Fut<'a, ()> {
+        Box::pin(async move {
+            let packed = packed(message.chat())?;
+            let input = self.input(outgoing).await?;
+            let client = self.current();
+            self.outbox
+                .queued(message.chat().id(), || {
+                    client.edit_message(packed, message.id(), input.clone())
+                })
+                .await?;
+            Ok(())
+        })
+    }
+
+    fn edit_now<'a>(&'a self, message: &'a Incoming, outgoing: Outgoing) -> Fut<'a, ()> {
+        Box::pin(async move {
+            let packed = packed(message.chat())?;
+            let input = self.input(outgoing).await?;
+            self.current()
+                .edit_message(packed, message.id(), input)
+                .await?;
+            Ok(())
+        })
+    }
+
+    fn reply_to_message<'a>(&'a self, message: &'a Incoming) -> Fut<'a, Option<Incoming>> {
+        Box::pin(async move {
+            let Some(id) = message.reply_to_message_id() else {
+                return Ok(None);
+            };
+            Ok(self.fetch(message.chat(), id).await?.map(|m| incoming(&m)))
+        })
+    }
+
+    fn download_media<'a>(&'a self, message: &'a Incoming) -> Fut<'a, Option<Vec<u8>>> {
+        Box::pin(async move {
+            if message.document().is_none() {
+                return Ok(None);
+            }
+            let Some(media) = self
+                .fetch(message.chat(), message.id())
+                .await?
+                .and_then(|m| m.media())
+            else {
+                return Ok(None);
+            };
+            let mut contents = Vec::new();
+            let client = self.current();
+            let mut download = client.iter_download(&media);
+            while let Some(chunk) = download.next().await? {
+                contents.extend(chunk);
+            }
+            Ok(Some(contents))
+        })
+    }
+
+    fn is_chat_admin<'a>(&'a self, chat: &'a Peer, user: &'a Peer) -> Fut<'a, bool> {
+        Box::pin(async move {
+            // Everyone administers their own private chat with the bot, and
+            // anonymous admins send as the group itself.
+            if chat.kind() == PeerKind::User || user.id() == chat.id() {
+                return Ok(true);
+            }
+            let permissions = self
+                .current()
+                .get_permissions(packed(chat)?, packed(user)?)
+                .await?;
+            Ok(permissions.is_creator() || permissions.is_admin())
+        })
+    }
+
+    fn stats(&self) -> Option<OutboxStats> {
+        Some(self.outbox.stats())
+    }
+
+    fn client(&self) -> Option<Client> {
+        Some(self.current())
+    }
+}
+
+/// A message as plugins see it.
+pub fn incoming(message: &Message) -> Incoming {
+    let incoming = Incoming::new(
+        message.id(),
+        peer(&message.chat()),
+        message.sender().map(|sender| peer(&sender)),
+        message.text(),
+    )
+    .in_reply_to(message.reply_to_message_id());
+    match message.media() {
+        Some(Media::Document(document)) => incoming.with_document(Document {
+            name: document.name().to_owned(),
+            size: document.size(),
+        }),
+        _ => incoming,
+    }
+}
+
+pub fn peer(chat: &Chat) -> Peer {
+    let kind = match chat {
+        Chat::User(_) => PeerKind::User,
+        Chat::Group(_) => PeerKind::Group,
+        Chat::Channel(_) => PeerKind::Channel,
+    };
+    Peer::new(chat.id(), chat.name(), kind).with_packed(chat.pack())
+}
+
+/// Answers an inline query with [`inline::answer`]; Telegram caches the
+/// results for as long as the bot does.
+pub async fn answer_inline(
+    app: &Arc<AppContext>,
+    query: InlineQuery,
+) -> Result<(), TransportError> {
+    let results = inline::answer(app, query.sender().id(), query.text()).await;
+    let results = results
+        .iter()
+        .enumerate()
+        .map(|(id, result)| inline_result(id, result))
+        .collect::<Vec<_>>();
+    let cache_secs = app.config().inline.cache_secs;
+    query
+        .answer(results)
+        .cache_time(cache_secs.try_into().unwrap_or(i32::MAX))
+        .send()
+        .await?;
+    Ok(())
+}
+
+/// Builds the raw result, as grammers has no builder for those sending
+/// arbitrary messages.
+fn inline_result(id: usize, result: &InlineResult) -> tl::enums::InputBotInlineResult {
+    let outgoing = &result.message;
+    let (message, entities) = match outgoing.format {
+        Format::Plain => (outgoing.text.clone(), Vec::new()),
+        Format::Html => parsers::parse_html_message(&outgoing.text),
+        Format::Markdown => parsers::parse_markdown_message(&outgoing.text),
+    };
+    let entities = (!entities.is_empty()).then_some(entities);
+    let reply_markup: Option<tl::enums::ReplyMarkup> = (!outgoing.buttons.is_empty()).then(|| {
+        let rows = outgoing
+            .buttons
+            .iter()
+            .map(|row| {
+                let buttons = row
+                    .iter()
+                    .map(|b| {
+                        tl::types::KeyboardButtonUrl {
+                            text: b.text.clone(),
+                            url: b.url.clone(),
+                        }
+                        .into()
+                    })
+                    .collect();
+                tl::types::KeyboardButtonRow { buttons }.into()
+            })
+            .collect();
+        tl::types::ReplyInlineMarkup { rows }.into()
+    });
+
+    let (kind, content, send_message): (
+        _,
+        Option<tl::enums::InputWebDocument>,
+        tl::enums::InputBotInlineMessage,
+    ) = match &outgoing.photo_url {
+        Some(url) => {
+            let photo = tl::types::InputWebDocument {
+                url: url.clone(),
+                size: 0,
+                mime_type: "image/jpeg".to_owned(),
+                attributes: Vec::new(),
+            };
+            let send_message = tl::types::InputBotInlineMessageMediaAuto {
+                invert_media: false,
+                message,
+                entities,
+                reply_markup,
+            };
+            ("photo", Some(photo.into()), send_message.into())
+        }
+        None => {
+            let send_message = tl::types::InputBotInlineMessageText {
+                no_webpage: false,
+                invert_media: false,
+                message,
+                entities,
+                reply_markup,
+            };
+            ("article", None, send_message.into())
+        }
+    };
+    tl::types::InputBotInlineResult {
+        id: id.to_string(),
+        r#type: kind.to_owned(),
+        title: Some(result.title.clone()),
+        description: (!result.description.is_empty()).then(|| result.description.clone()),
+        url: None,
+        thumb: content.clone(),
+        content,
+        send_message,
+    }
+    .into()
+}
+
+/// Turns a Bot API style chat ID (`-100…` for channels and supergroups,
+/// negative for basic groups) into a chat we can send to.
+pub fn bot_api_peer(id: i64) -> Peer {
+    const CHANNEL_OFFSET: i64 = 1_000_000_000_000;
+    let (ty, kind, id) = if id < -CHANNEL_OFFSET {
+        (
+            PackedType::Megagroup,
+            PeerKind::Channel,
+            -id - CHANNEL_OFFSET,
+        )
+    } else if id < 0 {
+        (PackedType::Chat, PeerKind::Group, -id)
+    } else {
+        (PackedType::User, PeerKind::User, id)
+    };
+    let packed = PackedChat {
+        ty,
+        id,
+        access_hash: None,
+    };
+    Peer::new(id, id.to_string(), kind).with_packed(packed)
+}
+
+fn packed(chat: &Peer) -> Result<PackedChat, TransportError> {
+    chat.packed().ok_or(TransportError::UnknownChat(chat.id()))
+}